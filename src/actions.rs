@@ -40,6 +40,11 @@ pub enum Action {
     ShowDiff,
     GenerateMessage,
     VersionUpdate,
+    FocusShell,
+    NextTask,
+    CancelTask,
+    DropQueued,
+    RespawnShell,
 }
 
 impl Action {
@@ -65,6 +70,11 @@ impl Action {
             Action::ShowDiff => "Show the git diff as JSON",
             Action::GenerateMessage => "Generate commit messages with AI",
             Action::VersionUpdate => "Increment patch version",
+            Action::FocusShell => "Send all keystrokes to the shell",
+            Action::NextTask => "Switch between the shell and task output",
+            Action::CancelTask => "Cancel the running task",
+            Action::DropQueued => "Remove the last queued action, or one by name or number",
+            Action::RespawnShell => "Start a new shell after the old one exited",
         }
     }
 
@@ -90,6 +100,11 @@ impl Action {
             Action::ShowDiff => "/diff",
             Action::GenerateMessage => "/message",
             Action::VersionUpdate => "/ver",
+            Action::FocusShell => "/shell",
+            Action::NextTask => "/task",
+            Action::CancelTask => "/cancel",
+            Action::DropQueued => "/unqueue",
+            Action::RespawnShell => "/respawn",
         }
     }
 
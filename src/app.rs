@@ -2,12 +2,22 @@
 
 use crate::actions::Action;
 use crate::config::{Config, Keybind};
+use crate::event::Waker;
 use crate::history::CommandHistory;
 use crate::project::ProjectConfig;
+use crate::task::{Task, TaskSpec};
 use crate::terminal::VirtualTerminal;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
 use strum::IntoEnumIterator;
 
+/// Ctrl+C is ignored this long after a task exits, so a repeated press does not quit.
+const CTRL_C_QUIT_GRACE: Duration = Duration::from_secs(1);
+
+const QUICK_SHELL_EXIT: Duration = Duration::from_secs(2);
+
+const MAX_QUICK_SHELL_EXITS: u32 = 3;
+
 #[derive(PartialEq)]
 pub enum BottomBarMode {
     Tips,
@@ -29,25 +39,140 @@ pub enum HelpConflictDialogSelection {
     Inspect,
 }
 
+#[derive(Clone, Copy, PartialEq)]
 pub enum ScriptEndStatus {
     Finished,
-    Cancelled,
+    Failed(i32),
+    Signalled(i32),
+    /// Cancelled by the user, and ended by this signal.
+    Cancelled(i32),
+}
+
+impl ScriptEndStatus {
+    /// Values above 128 mean the command died from signal `status - 128`.
+    pub fn from_exit_code(code: Option<i32>) -> Self {
+        match code {
+            None | Some(0) => Self::Finished,
+            Some(code @ 129..=192) => Self::Signalled(code - 128),
+            Some(code) => Self::Failed(code),
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self {
+            Self::Finished => "ok".to_string(),
+            Self::Failed(code) => format!("exit {}", code),
+            Self::Signalled(signal) => signal_label(*signal),
+            Self::Cancelled(signal) => format!("cancelled, {}", signal_label(*signal)),
+        }
+    }
+}
+
+fn end_log_entry(kind: &str, name: &str, status: ScriptEndStatus) -> LogEntry {
+    match status {
+        ScriptEndStatus::Finished => {
+            LogEntry::new(LogLevel::Success, format!("{} '{}' finished.", kind, name))
+        }
+        ScriptEndStatus::Failed(code) => LogEntry::new(
+            LogLevel::Error,
+            format!("{} '{}' failed with exit code {}.", kind, name, code),
+        ),
+        ScriptEndStatus::Signalled(signal) => LogEntry::new(
+            LogLevel::Error,
+            format!(
+                "{} '{}' was killed by {}.",
+                kind,
+                name,
+                signal_label(signal)
+            ),
+        ),
+        ScriptEndStatus::Cancelled(signal) => LogEntry::new(
+            LogLevel::Warning,
+            format!(
+                "{} '{}' cancelled, ended by {}.",
+                kind,
+                name,
+                signal_label(signal)
+            ),
+        ),
+    }
+}
+
+pub fn signal_label(signal: i32) -> String {
+    let name = match signal {
+        1 => "SIGHUP",
+        2 => "SIGINT",
+        3 => "SIGQUIT",
+        6 => "SIGABRT",
+        9 => "SIGKILL",
+        11 => "SIGSEGV",
+        13 => "SIGPIPE",
+        15 => "SIGTERM",
+        _ => return format!("signal {}", signal),
+    };
+    format!("{} ({})", name, signal)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LogLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+pub struct LogEntry {
+    pub message: String,
+    pub level: LogLevel,
+}
+
+impl LogEntry {
+    pub fn new(level: LogLevel, message: String) -> Self {
+        Self { message, level }
+    }
+}
+
+impl From<String> for LogEntry {
+    fn from(message: String) -> Self {
+        Self::new(LogLevel::Info, message)
+    }
+}
+
+impl From<&str> for LogEntry {
+    fn from(message: &str) -> Self {
+        Self::new(LogLevel::Info, message.to_string())
+    }
 }
 
 pub struct App {
     pub terminal: VirtualTerminal,
-    pub logs: Vec<String>,
+    pub logs: Vec<LogEntry>,
     pub bottom_bar_mode: BottomBarMode,
     pub should_quit: bool,
     pub command_input: String,
+    /// Counted in characters.
     pub command_cursor_position: usize,
     pub command_history: CommandHistory,
     pub config: Config,
     pub project_config: Option<ProjectConfig>,
-    pub is_script_running: bool,
-    pub current_script: String,
-    pub status_message: String,
+    pub waker: Waker,
+    pub tasks: Vec<Task>,
+    /// `None` when the shell is shown.
+    pub viewed_task: Option<usize>,
+    pub queued_tasks: VecDeque<TaskSpec>,
+    /// Command line sent to the shell from the palette, until it finishes.
+    pub shell_command: Option<String>,
+    pub shell_command_executing: bool,
+    /// `None` while the shell is running.
+    pub shell_status: Option<ScriptEndStatus>,
+    pub should_respawn_shell: bool,
+    shell_started_at: Instant,
+    quick_shell_exits: u32,
+    pub last_script_status: Option<(String, ScriptEndStatus)>,
+    last_task_exit: Option<Instant>,
     pub input_context: Option<InputContext>,
+    /// When set, every key press goes to the shell until the escape chord is pressed.
+    pub shell_focused: bool,
     // Help screen state
     pub show_help: bool,
     pub help_selected_action_index: usize,
@@ -64,6 +189,7 @@ impl App {
         rows: u16,
         config: Config,
         project_config: Option<ProjectConfig>,
+        waker: Waker,
     ) -> Self {
         let mut sorted_actions: Vec<Action> = Action::iter().collect();
         sorted_actions.sort_by(|a, b| {
@@ -80,7 +206,7 @@ impl App {
         });
 
         App {
-            terminal: VirtualTerminal::new(rows, cols),
+            terminal: VirtualTerminal::new(rows, cols, config.scrollback_lines),
             logs: Vec::new(),
             bottom_bar_mode: BottomBarMode::Tips,
             should_quit: false,
@@ -89,10 +215,20 @@ impl App {
             command_history,
             config,
             project_config,
-            is_script_running: false,
-            current_script: String::new(),
-            status_message: String::new(),
+            waker,
+            tasks: Vec::new(),
+            viewed_task: None,
+            queued_tasks: VecDeque::new(),
+            shell_command: None,
+            shell_command_executing: false,
+            shell_status: None,
+            should_respawn_shell: false,
+            shell_started_at: Instant::now(),
+            quick_shell_exits: 0,
+            last_script_status: None,
+            last_task_exit: None,
             input_context: None,
+            shell_focused: false,
             show_help: false,
             help_selected_action_index: 0,
             is_editing_keybinding: false,
@@ -104,10 +240,23 @@ impl App {
     }
 
     pub fn scroll_up(&mut self) {
-        self.terminal.scroll_up(1);
+        self.viewed_terminal_mut().scroll_up(1);
     }
     pub fn scroll_down(&mut self) {
-        self.terminal.scroll_down(1);
+        self.viewed_terminal_mut().scroll_down(1);
+    }
+
+    pub fn viewed_terminal(&self) -> &VirtualTerminal {
+        match self.viewed_task {
+            Some(index) => &self.tasks[index].terminal,
+            None => &self.terminal,
+        }
+    }
+    pub fn viewed_terminal_mut(&mut self) -> &mut VirtualTerminal {
+        match self.viewed_task {
+            Some(index) => &mut self.tasks[index].terminal,
+            None => &mut self.terminal,
+        }
     }
     pub fn move_cursor_left(&mut self) {
         self.command_cursor_position = self.command_cursor_position.saturating_sub(1);
@@ -116,13 +265,24 @@ impl App {
         self.command_cursor_position = self
             .command_cursor_position
             .saturating_add(1)
-            .min(self.command_input.len());
+            .min(self.command_input.chars().count());
     }
-    pub fn enter_char(&mut self, new_char: char) {
+    pub fn command_cursor_byte_index(&self) -> usize {
         self.command_input
-            .insert(self.command_cursor_position, new_char);
+            .char_indices()
+            .nth(self.command_cursor_position)
+            .map_or(self.command_input.len(), |(index, _)| index)
+    }
+    pub fn enter_char(&mut self, new_char: char) {
+        let index = self.command_cursor_byte_index();
+        self.command_input.insert(index, new_char);
         self.move_cursor_right();
     }
+    pub fn enter_str(&mut self, text: &str) {
+        let index = self.command_cursor_byte_index();
+        self.command_input.insert_str(index, text);
+        self.command_cursor_position += text.chars().count();
+    }
     pub fn delete_char(&mut self) {
         if self.command_cursor_position > 0 {
             let current_idx = self.command_cursor_position;
@@ -139,20 +299,25 @@ impl App {
         }
         self.command_input.clear();
         self.command_cursor_position = 0;
-        self.bottom_bar_mode = BottomBarMode::Tips;
+        // Go back to the Status bar while tasks run, so the queue stays in view.
+        self.bottom_bar_mode = if self.running_task().is_some() {
+            BottomBarMode::Status
+        } else {
+            BottomBarMode::Tips
+        };
     }
 
     pub fn navigate_history_up(&mut self) {
         if let Some(command) = self.command_history.navigate_up(&self.command_input) {
             self.command_input = command;
-            self.command_cursor_position = self.command_input.len();
+            self.command_cursor_position = self.command_input.chars().count();
         }
     }
 
     pub fn navigate_history_down(&mut self) {
         if let Some(command) = self.command_history.navigate_down() {
             self.command_input = command;
-            self.command_cursor_position = self.command_input.len();
+            self.command_cursor_position = self.command_input.chars().count();
         }
     }
 
@@ -160,25 +325,208 @@ impl App {
         self.command_history.reset_navigation();
     }
 
-    pub fn start_script(&mut self, name: &str, status_msg: &str) {
-        self.is_script_running = true;
-        self.current_script = name.to_string();
-        self.status_message = status_msg.to_string();
-        self.bottom_bar_mode = BottomBarMode::Status;
+    /// A finished run of the same script is replaced.
+    pub fn start_task(&mut self, task: Task) {
+        if let Some(index) = self
+            .tasks
+            .iter()
+            .position(|t| t.name == task.name && !t.is_running())
+        {
+            self.tasks.remove(index);
+        }
         self.logs
-            .push(format!("Script '{}' running...", self.current_script));
+            .push(format!("Script '{}' running...", task.name).into());
+        self.tasks.push(task);
+        self.viewed_task = Some(self.tasks.len() - 1);
+        self.bottom_bar_mode = BottomBarMode::Status;
+    }
+
+    pub fn finish_task(&mut self, index: usize, status: ScriptEndStatus) {
+        let name = self.tasks[index].name.clone();
+        self.logs.push(end_log_entry("Script", &name, status));
+        self.last_script_status = Some((name, status));
+        self.last_task_exit = Some(Instant::now());
+        if self.bottom_bar_mode == BottomBarMode::Status
+            && self.running_task().is_none()
+            && self.queued_tasks.is_empty()
+        {
+            self.bottom_bar_mode = BottomBarMode::Tips;
+        }
+    }
+
+    pub fn ctrl_c_quit_blocked(&self) -> bool {
+        self.last_task_exit
+            .is_some_and(|exit| exit.elapsed() < CTRL_C_QUIT_GRACE)
+    }
+
+    /// The task is only marked cancelled once it has exited.
+    pub fn cancel_task(&mut self, index: usize) {
+        let timeouts = self.config.cancel_timeouts();
+        let task = &mut self.tasks[index];
+        let entry = match task.cancel(timeouts) {
+            Ok(signal) => format!(
+                "Cancelling script '{}': sent {}.",
+                task.name,
+                signal_label(signal)
+            )
+            .into(),
+            Err(err) => LogEntry::new(
+                LogLevel::Error,
+                format!("Failed to cancel script '{}': {}", task.name, err),
+            ),
+        };
+        self.logs.push(entry);
+    }
+
+    /// The viewed task if it is running, otherwise the most recently started one.
+    pub fn running_task(&self) -> Option<usize> {
+        self.viewed_task
+            .filter(|&index| self.tasks[index].is_running())
+            .or_else(|| self.tasks.iter().rposition(Task::is_running))
+    }
+
+    pub fn blocking_task(&self) -> Option<usize> {
+        self.tasks
+            .iter()
+            .rposition(|task| task.exclusive && task.is_running())
+    }
+
+    pub fn queue_task(&mut self, spec: TaskSpec) {
+        if let Some(index) = self.blocking_task() {
+            self.logs.push(
+                format!(
+                    "Script '{}' queued until '{}' finishes.",
+                    spec.name, self.tasks[index].name
+                )
+                .into(),
+            );
+        }
+        self.queued_tasks.push_back(spec);
+    }
+
+    pub fn next_queued_task(&mut self) -> Option<TaskSpec> {
+        if self.blocking_task().is_some() {
+            return None;
+        }
+        self.queued_tasks.pop_front()
+    }
+
+    /// Drops the entry at a one-based position, the last one with a given name, or the last one.
+    pub fn drop_queued_task(&mut self, target: Option<&str>) {
+        if self.queued_tasks.is_empty() {
+            self.logs.push(LogEntry::new(
+                LogLevel::Warning,
+                "No queued scripts to remove.".to_string(),
+            ));
+            return;
+        }
+        let index = match target {
+            None => Some(self.queued_tasks.len() - 1),
+            Some(target) => match target.parse::<usize>() {
+                Ok(position) => position
+                    .checked_sub(1)
+                    .filter(|&index| index < self.queued_tasks.len()),
+                Err(_) => self
+                    .queued_tasks
+                    .iter()
+                    .rposition(|spec| spec.name == target),
+            },
+        };
+        let entry = match index.and_then(|index| self.queued_tasks.remove(index)) {
+            Some(spec) => format!("Removed script '{}' from the queue.", spec.name).into(),
+            None => LogEntry::new(
+                LogLevel::Warning,
+                format!("No queued script matches '{}'.", target.unwrap_or_default()),
+            ),
+        };
+        self.logs.push(entry);
     }
 
-    pub fn finish_script(&mut self, status: ScriptEndStatus) {
-        let log_message = match status {
-            ScriptEndStatus::Finished => format!("Script '{}' finished.", self.current_script),
-            ScriptEndStatus::Cancelled => format!("Script '{}' cancelled.", self.current_script),
+    pub fn view_next_task(&mut self) {
+        self.viewed_task = match self.viewed_task {
+            None if !self.tasks.is_empty() => Some(0),
+            Some(index) if index + 1 < self.tasks.len() => Some(index + 1),
+            _ => None,
         };
-        self.logs.push(log_message);
-        self.is_script_running = false;
-        self.current_script.clear();
-        self.status_message.clear();
-        self.bottom_bar_mode = BottomBarMode::Tips;
+    }
+
+    pub fn finish_shell_command(&mut self, status: ScriptEndStatus) {
+        if let Some(command) = self.shell_command.take() {
+            self.logs.push(end_log_entry("Command", &command, status));
+        }
+        self.shell_command_executing = false;
+    }
+
+    pub fn shell_exited(&mut self, program: &str, status: ScriptEndStatus) {
+        self.logs.push(end_log_entry("Shell", program, status));
+        self.shell_status = Some(status);
+        self.shell_command = None;
+        self.shell_command_executing = false;
+        if self.shell_started_at.elapsed() < QUICK_SHELL_EXIT {
+            self.quick_shell_exits += 1;
+        } else {
+            self.quick_shell_exits = 0;
+        }
+        if self.config.auto_respawn_shell {
+            if self.quick_shell_exits < MAX_QUICK_SHELL_EXITS {
+                self.should_respawn_shell = true;
+                return;
+            }
+            self.logs.push(LogEntry::new(
+                LogLevel::Warning,
+                format!(
+                    "The shell exited right after starting {} times in a row, so it is not restarted automatically.",
+                    self.quick_shell_exits
+                ),
+            ));
+        }
+        self.offer_respawn();
+    }
+
+    pub fn shell_respawn_failed(&mut self, err: &anyhow::Error) {
+        self.should_respawn_shell = false;
+        self.logs.push(LogEntry::new(
+            LogLevel::Error,
+            format!("Failed to start a new shell: {:#}", err),
+        ));
+        self.offer_respawn();
+    }
+
+    fn offer_respawn(&mut self) {
+        // Keystrokes would go nowhere until a new shell is started.
+        if self.viewed_task.is_none() {
+            self.shell_focused = false;
+        }
+        let hint = match self.config.get_keybind(Action::RespawnShell) {
+            Some(Keybind::Char(c)) => format!("Press '{}' to start a new shell.", c),
+            _ => format!(
+                "Use {} to start a new shell.",
+                Action::RespawnShell.command_str()
+            ),
+        };
+        self.logs.push(hint.into());
+    }
+
+    pub fn respawn_shell(&mut self) {
+        if self.shell_status.is_some() {
+            self.quick_shell_exits = 0;
+            self.should_respawn_shell = true;
+        } else {
+            self.logs.push(LogEntry::new(
+                LogLevel::Warning,
+                "The shell is still running.".to_string(),
+            ));
+        }
+    }
+
+    pub fn shell_respawned(&mut self, program: &str) {
+        let (rows, cols) = self.terminal.size();
+        self.terminal = VirtualTerminal::new(rows, cols, self.config.scrollback_lines);
+        self.shell_status = None;
+        self.should_respawn_shell = false;
+        self.shell_started_at = Instant::now();
+        self.logs
+            .push(format!("Started a new shell '{}'.", program).into());
     }
 
     /// Check for keybinding conflicts and prepare to close help screen
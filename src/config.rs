@@ -1,11 +1,15 @@
 /* src/config.rs */
 
 use crate::actions::Action;
+use crate::keys;
+use crate::task::CancelTimeouts;
 use anyhow::{Context, Result};
+use crossterm::event::KeyEvent;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 use strum::IntoEnumIterator;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -14,9 +18,70 @@ pub enum Keybind {
     None,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConcurrencyPolicy {
+    Parallel,
+    Queue,
+    Reject,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShellConfig {
+    #[serde(default)]
+    pub program: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub init_file: Option<PathBuf>,
+}
+
+const DEFAULT_SCROLLBACK_LINES: usize = 10_000;
+
+const DEFAULT_SHELL_ESCAPE_KEY: &str = "ctrl+]";
+
+const DEFAULT_CANCEL_TERM_TIMEOUT_MS: u64 = 3_000;
+
+const DEFAULT_CANCEL_KILL_TIMEOUT_MS: u64 = 3_000;
+
+fn default_scrollback_lines() -> usize {
+    DEFAULT_SCROLLBACK_LINES
+}
+
+fn default_shell_escape_key() -> String {
+    DEFAULT_SHELL_ESCAPE_KEY.to_string()
+}
+
+fn default_cancel_term_timeout_ms() -> u64 {
+    DEFAULT_CANCEL_TERM_TIMEOUT_MS
+}
+
+fn default_cancel_kill_timeout_ms() -> u64 {
+    DEFAULT_CANCEL_KILL_TIMEOUT_MS
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub keybindings: HashMap<String, Keybind>,
+    #[serde(default = "default_scrollback_lines")]
+    pub scrollback_lines: usize,
+    #[serde(default = "default_shell_escape_key")]
+    pub shell_escape_key: String,
+    #[serde(default = "default_cancel_term_timeout_ms")]
+    pub cancel_term_timeout_ms: u64,
+    #[serde(default = "default_cancel_kill_timeout_ms")]
+    pub cancel_kill_timeout_ms: u64,
+    #[serde(default)]
+    pub concurrency: HashMap<String, ConcurrencyPolicy>,
+    #[serde(default)]
+    pub auto_respawn_shell: bool,
+    #[serde(default)]
+    pub shell: ShellConfig,
+    /// Actions left unbound because their default key was taken.
+    #[serde(skip)]
+    pub unbound_defaults: Vec<(Action, char)>,
 }
 
 impl Config {
@@ -56,7 +121,13 @@ impl Config {
         for action in Action::iter() {
             let action_str = action.to_string();
             if !self.keybindings.contains_key(&action_str) {
-                let keybind = Self::default_keybind_for_action(action);
+                let mut keybind = Self::default_keybind_for_action(action);
+                if let Keybind::Char(c) = keybind
+                    && self.keybindings.values().any(|bound| *bound == keybind)
+                {
+                    self.unbound_defaults.push((action, c));
+                    keybind = Keybind::None;
+                }
                 self.keybindings.insert(action_str, keybind);
             }
         }
@@ -74,10 +145,37 @@ impl Config {
             Action::AddPackage => Keybind::Char('a'),
             Action::RemovePackage => Keybind::Char('R'),
             Action::Commit => Keybind::Char('m'),
+            Action::FocusShell => Keybind::Char('s'),
+            Action::NextTask => Keybind::Char('t'),
+            Action::CancelTask => Keybind::Char('x'),
+            Action::DropQueued => Keybind::Char('u'),
+            Action::RespawnShell => Keybind::Char('n'),
             _ => Keybind::None,
         }
     }
 
+    pub fn shell_escape_chord(&self) -> KeyEvent {
+        keys::parse_chord(&self.shell_escape_key)
+            .or_else(|| keys::parse_chord(DEFAULT_SHELL_ESCAPE_KEY))
+            .expect("default shell escape key is valid")
+    }
+
+    /// The dev server runs alongside other scripts by default.
+    pub fn concurrency_policy(&self, action: Action) -> ConcurrencyPolicy {
+        match self.concurrency.get(&action.to_string()) {
+            Some(policy) => *policy,
+            None if action == Action::Run => ConcurrencyPolicy::Parallel,
+            None => ConcurrencyPolicy::Queue,
+        }
+    }
+
+    pub fn cancel_timeouts(&self) -> CancelTimeouts {
+        CancelTimeouts {
+            term_after: Duration::from_millis(self.cancel_term_timeout_ms),
+            kill_after: Duration::from_millis(self.cancel_kill_timeout_ms),
+        }
+    }
+
     pub fn get_keybind(&self, action: Action) -> Option<&Keybind> {
         self.keybindings.get(&action.to_string())
     }
@@ -99,6 +197,16 @@ impl Default for Config {
             let keybind = Self::default_keybind_for_action(action);
             keybindings.insert(action.to_string(), keybind);
         }
-        Self { keybindings }
+        Self {
+            keybindings,
+            scrollback_lines: DEFAULT_SCROLLBACK_LINES,
+            shell_escape_key: DEFAULT_SHELL_ESCAPE_KEY.to_string(),
+            cancel_term_timeout_ms: DEFAULT_CANCEL_TERM_TIMEOUT_MS,
+            cancel_kill_timeout_ms: DEFAULT_CANCEL_KILL_TIMEOUT_MS,
+            concurrency: HashMap::new(),
+            auto_respawn_shell: false,
+            shell: ShellConfig::default(),
+            unbound_defaults: Vec::new(),
+        }
     }
 }
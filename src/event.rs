@@ -0,0 +1,69 @@
+/* src/event.rs */
+
+use anyhow::{Result, bail};
+use crossterm::event::{self, Event};
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Something that needs the main loop's attention.
+pub enum AppEvent {
+    Input(Event),
+    Output,
+    InputError(io::Error),
+}
+
+/// Lets the PTY reader threads wake the main loop.
+#[derive(Clone)]
+pub struct Waker(Sender<AppEvent>);
+
+impl Waker {
+    pub fn wake(&self) {
+        // The loop is gone once the receiver is dropped, and nobody needs waking.
+        let _ = self.0.send(AppEvent::Output);
+    }
+}
+
+pub struct EventQueue {
+    sender: Sender<AppEvent>,
+    receiver: Receiver<AppEvent>,
+}
+
+impl EventQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let input_sender = sender.clone();
+        thread::spawn(move || {
+            loop {
+                let event = match event::read() {
+                    Ok(event) => AppEvent::Input(event),
+                    Err(err) => {
+                        let _ = input_sender.send(AppEvent::InputError(err));
+                        break;
+                    }
+                };
+                if input_sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { sender, receiver }
+    }
+
+    pub fn waker(&self) -> Waker {
+        Waker(self.sender.clone())
+    }
+
+    /// Waits for an event or `timeout`, then drains the queue so a burst is handled in one pass.
+    pub fn wait(&self, timeout: Duration) -> Result<Vec<AppEvent>> {
+        let first = match self.receiver.recv_timeout(timeout) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => return Ok(Vec::new()),
+            Err(RecvTimeoutError::Disconnected) => bail!("Event queue closed"),
+        };
+        let mut events = vec![first];
+        events.extend(self.receiver.try_iter());
+        Ok(events)
+    }
+}
@@ -0,0 +1,160 @@
+/* src/integration.rs */
+
+use anyhow::{Context, Result};
+use portable_pty::CommandBuilder;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Each snippet loads the user's own startup files first, then `$CLAY_INIT_FILE` if set, and
+// then reports OSC 133 marks:
+// `A` before the prompt, `B` after it, `C` when a command starts and `D;<status>` when it ends.
+
+const BASH_INTEGRATION: &str = r#"# Clay shell integration for bash, loaded through --rcfile.
+if [ -f ~/.bashrc ]; then . ~/.bashrc; fi
+if [ -n "$CLAY_INIT_FILE" ] && [ -f "$CLAY_INIT_FILE" ]; then . "$CLAY_INIT_FILE"; fi
+
+__clay_prompt() {
+    local status=$?
+    printf '\033]133;D;%s\007\033]133;A\007' "$status"
+    return $status
+}
+PROMPT_COMMAND="__clay_prompt${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+PS0+='\e]133;C\a'
+PS1+='\[\e]133;B\a\]'
+"#;
+
+const ZSH_ENV: &str = r#"# Clay shell integration for zsh. ZDOTDIR points here until .zshrc has been read.
+__clay_zdotdir=$ZDOTDIR
+ZDOTDIR=$CLAY_USER_ZDOTDIR
+[[ -f $ZDOTDIR/.zshenv ]] && source $ZDOTDIR/.zshenv
+CLAY_USER_ZDOTDIR=$ZDOTDIR
+ZDOTDIR=$__clay_zdotdir
+"#;
+
+const ZSH_PROFILE: &str = r#"# Clay shell integration for zsh, read by login shells before .zshrc.
+ZDOTDIR=$CLAY_USER_ZDOTDIR
+[[ -f $ZDOTDIR/.zprofile ]] && source $ZDOTDIR/.zprofile
+CLAY_USER_ZDOTDIR=$ZDOTDIR
+ZDOTDIR=$__clay_zdotdir
+"#;
+
+const ZSH_RC: &str = r#"# Clay shell integration for zsh.
+ZDOTDIR=$CLAY_USER_ZDOTDIR
+unset CLAY_USER_ZDOTDIR __clay_zdotdir
+[[ -f $ZDOTDIR/.zshrc ]] && source $ZDOTDIR/.zshrc
+[[ -n $CLAY_INIT_FILE && -f $CLAY_INIT_FILE ]] && source $CLAY_INIT_FILE
+
+__clay_precmd() {
+    local ret=$?
+    printf '\033]133;D;%s\007\033]133;A\007' "$ret"
+}
+__clay_preexec() {
+    printf '\033]133;C\007'
+}
+precmd_functions=(__clay_precmd $precmd_functions)
+preexec_functions+=(__clay_preexec)
+PS1="$PS1%{"$'\e]133;B\a'"%}"
+"#;
+
+const FISH_INTEGRATION: &str = r#"# Clay shell integration for fish, loaded through --init-command.
+if set -q CLAY_INIT_FILE; and test -f "$CLAY_INIT_FILE"
+    source "$CLAY_INIT_FILE"
+end
+
+function __clay_prompt --on-event fish_prompt
+    printf '\e]133;A\a'
+end
+function __clay_preexec --on-event fish_preexec
+    printf '\e]133;C\a'
+end
+function __clay_postexec --on-event fish_postexec
+    printf '\e]133;D;%s\a' $status
+end
+"#;
+
+/// Points `shell` at Clay's OSC 133 hooks and `init_file`. Returns `false` when it has no hooks.
+pub fn install(
+    cmd: &mut CommandBuilder,
+    shell: &str,
+    args: &[String],
+    init_file: Option<&Path>,
+) -> Result<bool> {
+    let name = shell_name(shell);
+    let dir = integration_dir()?;
+    if let Some(init_file) = init_file {
+        cmd.env("CLAY_INIT_FILE", init_file);
+    }
+
+    match name {
+        "bash" if is_login(args) => return Ok(false),
+        "bash" => {
+            let rcfile = write_snippet(&dir, "clay.bash", BASH_INTEGRATION)?;
+            cmd.arg("--rcfile");
+            cmd.arg(rcfile);
+        }
+        "zsh" => {
+            let zdotdir = dir.join("zsh");
+            write_snippet(&zdotdir, ".zshenv", ZSH_ENV)?;
+            write_snippet(&zdotdir, ".zprofile", ZSH_PROFILE)?;
+            write_snippet(&zdotdir, ".zshrc", ZSH_RC)?;
+            let user_zdotdir = std::env::var_os("ZDOTDIR")
+                .or_else(|| std::env::var_os("HOME"))
+                .unwrap_or_default();
+            cmd.env("CLAY_USER_ZDOTDIR", user_zdotdir);
+            cmd.env("ZDOTDIR", zdotdir);
+        }
+        "fish" => {
+            let script = write_snippet(&dir, "clay.fish", FISH_INTEGRATION)?;
+            let quoted = script
+                .to_string_lossy()
+                .replace('\\', "\\\\")
+                .replace('\'', "\\'");
+            cmd.arg("--init-command");
+            cmd.arg(format!("source '{}'", quoted));
+        }
+        _ => {
+            if let Some(init_file) = init_file {
+                cmd.env("ENV", init_file);
+            }
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// A bash login shell ignores `--rcfile`, so `init_file` is typed in instead.
+pub fn startup_input(shell: &str, args: &[String], init_file: Option<&Path>) -> Option<String> {
+    let init_file = init_file?;
+    if shell_name(shell) != "bash" || !is_login(args) {
+        return None;
+    }
+    let quoted = init_file.to_string_lossy().replace('\'', r"'\''");
+    Some(format!(". '{}'\n", quoted))
+}
+
+fn shell_name(shell: &str) -> &str {
+    Path::new(shell)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(shell)
+}
+
+fn is_login(args: &[String]) -> bool {
+    args.iter().any(|arg| {
+        arg == "--login"
+            || (arg.starts_with('-') && !arg.starts_with("--") && arg[1..].contains('l'))
+    })
+}
+
+fn integration_dir() -> Result<PathBuf> {
+    let base_dirs = directories::BaseDirs::new().context("Could not find home directory")?;
+    Ok(base_dirs.home_dir().join(".clay/shell"))
+}
+
+fn write_snippet(dir: &Path, file_name: &str, content: &str) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let path = dir.join(file_name);
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write shell integration to {}", path.display()))?;
+    Ok(path)
+}
@@ -0,0 +1,202 @@
+/* src/keys.rs */
+
+use crate::terminal::TermMode;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEventKind};
+
+/// Encodes a key press as an xterm would. `application_cursor` selects SS3 cursor keys (DECCKM).
+pub fn encode_key(key: KeyEvent, application_cursor: bool) -> Option<Vec<u8>> {
+    let modifiers = key.modifiers;
+    let alt = modifiers.contains(KeyModifiers::ALT);
+    let ctrl = modifiers.contains(KeyModifiers::CONTROL);
+    // xterm's modifier parameter: 1 + Shift(1) + Alt(2) + Ctrl(4).
+    let modifier_param = 1
+        + u8::from(modifiers.contains(KeyModifiers::SHIFT))
+        + 2 * u8::from(alt)
+        + 4 * u8::from(ctrl);
+
+    let mut bytes = match key.code {
+        KeyCode::Char(c) => {
+            let mut bytes = if ctrl {
+                vec![control_byte(c)?]
+            } else {
+                c.to_string().into_bytes()
+            };
+            if alt {
+                bytes.insert(0, 0x1b);
+            }
+            return Some(bytes);
+        }
+        KeyCode::Enter => b"\r".to_vec(),
+        KeyCode::Tab => b"\t".to_vec(),
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Backspace if ctrl => vec![0x08],
+        KeyCode::Backspace => vec![0x7f],
+        KeyCode::Esc => vec![0x1b],
+        KeyCode::Up => cursor_key(b'A', modifier_param, application_cursor),
+        KeyCode::Down => cursor_key(b'B', modifier_param, application_cursor),
+        KeyCode::Right => cursor_key(b'C', modifier_param, application_cursor),
+        KeyCode::Left => cursor_key(b'D', modifier_param, application_cursor),
+        KeyCode::Home => cursor_key(b'H', modifier_param, application_cursor),
+        KeyCode::End => cursor_key(b'F', modifier_param, application_cursor),
+        KeyCode::Insert => tilde_key(2, modifier_param),
+        KeyCode::Delete => tilde_key(3, modifier_param),
+        KeyCode::PageUp => tilde_key(5, modifier_param),
+        KeyCode::PageDown => tilde_key(6, modifier_param),
+        KeyCode::F(n @ 1..=4) => {
+            let final_byte = b'P' + (n - 1);
+            if modifier_param > 1 {
+                format!("\x1b[1;{}{}", modifier_param, final_byte as char).into_bytes()
+            } else {
+                vec![0x1b, b'O', final_byte]
+            }
+        }
+        KeyCode::F(n @ 5..=12) => {
+            let code = [15, 17, 18, 19, 20, 21, 23, 24][(n - 5) as usize];
+            tilde_key(code, modifier_param)
+        }
+        _ => return None,
+    };
+
+    // Alt on keys without a modifier parameter is sent as an ESC prefix.
+    if alt && matches!(key.code, KeyCode::Enter | KeyCode::Tab | KeyCode::Backspace) {
+        bytes.insert(0, 0x1b);
+    }
+    Some(bytes)
+}
+
+/// Returns `None` when the active tracking mode does not report this event.
+pub fn encode_mouse(
+    kind: MouseEventKind,
+    modifiers: KeyModifiers,
+    col: u16,
+    row: u16,
+    mode: TermMode,
+) -> Option<Vec<u8>> {
+    let motion_allowed = mode.intersects(TermMode::MOUSE_DRAG | TermMode::MOUSE_MOTION);
+    let (button, released) = match kind {
+        MouseEventKind::Down(button) => (button_code(button), false),
+        MouseEventKind::Up(button) => (button_code(button), true),
+        MouseEventKind::Drag(button) if motion_allowed => (button_code(button) + 32, false),
+        MouseEventKind::Moved if mode.contains(TermMode::MOUSE_MOTION) => (3 + 32, false),
+        MouseEventKind::ScrollUp => (64, false),
+        MouseEventKind::ScrollDown => (65, false),
+        MouseEventKind::ScrollLeft => (66, false),
+        MouseEventKind::ScrollRight => (67, false),
+        _ => return None,
+    };
+
+    let mut code = button;
+    if modifiers.contains(KeyModifiers::SHIFT) {
+        code += 4;
+    }
+    if modifiers.contains(KeyModifiers::ALT) {
+        code += 8;
+    }
+    if modifiers.contains(KeyModifiers::CONTROL) {
+        code += 16;
+    }
+
+    let (x, y) = (col as u32 + 1, row as u32 + 1);
+    if mode.contains(TermMode::MOUSE_SGR) {
+        let final_char = if released { 'm' } else { 'M' };
+        return Some(format!("\x1b[<{};{};{}{}", code, x, y, final_char).into_bytes());
+    }
+
+    // The legacy encoding has no release button; it also cannot address past column 223.
+    if released {
+        code = 3 + (code & !3);
+    }
+    if x > 223 || y > 223 {
+        return None;
+    }
+    Some(vec![
+        0x1b,
+        b'[',
+        b'M',
+        (32 + code) as u8,
+        (32 + x) as u8,
+        (32 + y) as u8,
+    ])
+}
+
+fn button_code(button: MouseButton) -> u32 {
+    match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+    }
+}
+
+pub fn parse_chord(chord: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut parts: Vec<&str> = chord.split('+').collect();
+    let key = parts.pop()?.trim();
+
+    for part in parts {
+        match part.trim().to_ascii_lowercase().as_str() {
+            "ctrl" | "control" => modifiers |= KeyModifiers::CONTROL,
+            "alt" | "meta" => modifiers |= KeyModifiers::ALT,
+            "shift" => modifiers |= KeyModifiers::SHIFT,
+            _ => return None,
+        }
+    }
+
+    let code = match key.to_ascii_lowercase().as_str() {
+        "esc" | "escape" => KeyCode::Esc,
+        "enter" => KeyCode::Enter,
+        "tab" => KeyCode::Tab,
+        "space" => KeyCode::Char(' '),
+        name if name.len() > 1 && name.starts_with('f') => KeyCode::F(name[1..].parse().ok()?),
+        _ => {
+            let mut chars = key.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            KeyCode::Char(c)
+        }
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+/// Compares encodings, since crossterm reports `ctrl+]` as `ctrl+5`.
+pub fn same_key(a: KeyEvent, b: KeyEvent) -> bool {
+    match (encode_key(a, false), encode_key(b, false)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.code == b.code && a.modifiers == b.modifiers,
+    }
+}
+
+fn control_byte(c: char) -> Option<u8> {
+    let byte = match c {
+        'a'..='z' => c as u8 - b'a' + 1,
+        'A'..='Z' => c as u8 - b'A' + 1,
+        '@' | ' ' | '2' => 0x00,
+        '[' | '3' => 0x1b,
+        '\\' | '4' => 0x1c,
+        ']' | '5' => 0x1d,
+        '^' | '6' => 0x1e,
+        '_' | '/' | '7' => 0x1f,
+        '?' | '8' => 0x7f,
+        _ => return None,
+    };
+    Some(byte)
+}
+
+fn cursor_key(final_byte: u8, modifier_param: u8, application_cursor: bool) -> Vec<u8> {
+    if modifier_param > 1 {
+        format!("\x1b[1;{}{}", modifier_param, final_byte as char).into_bytes()
+    } else if application_cursor {
+        vec![0x1b, b'O', final_byte]
+    } else {
+        vec![0x1b, b'[', final_byte]
+    }
+}
+
+fn tilde_key(code: u8, modifier_param: u8) -> Vec<u8> {
+    if modifier_param > 1 {
+        format!("\x1b[{};{}~", code, modifier_param).into_bytes()
+    } else {
+        format!("\x1b[{}~", code).into_bytes()
+    }
+}
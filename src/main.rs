@@ -5,11 +5,15 @@ mod app;
 mod commit;
 mod config;
 mod diff;
+mod event;
 mod history;
+mod integration;
+mod keys;
 mod lint;
 mod llm;
 mod project;
 mod shell;
+mod task;
 mod terminal;
 mod tui;
 mod ui;
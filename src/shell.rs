@@ -1,21 +1,99 @@
 /* src/shell.rs */
 
+use crate::config::ShellConfig;
+use crate::event::Waker;
+use crate::integration;
 use anyhow::{Result, bail};
-use portable_pty::{Child, CommandBuilder, PtySize, native_pty_system};
+use portable_pty::{Child, CommandBuilder, ExitStatus, MasterPty, PtySize, native_pty_system};
 use std::io::{self, Read, Write};
 use std::process::Command;
-use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver};
 use std::thread::{self, JoinHandle};
 
+#[cfg(not(windows))]
+const FALLBACK_EXECUTED_MARK: &str = r"printf '\033]133;C\007'; ";
+#[cfg(not(windows))]
+const FALLBACK_FINISHED_MARK: &str = r#"printf '\033]133;D;%s\007' "$?""#;
+#[cfg(windows)]
+const FALLBACK_EXECUTED_MARK: &str = r#"Write-Host -NoNewline "$([char]27)]133;C$([char]7)"; "#;
+#[cfg(windows)]
+const FALLBACK_FINISHED_MARK: &str =
+    r#"Write-Host -NoNewline "$([char]27)]133;D;$LASTEXITCODE$([char]7)""#;
+
+#[cfg(not(windows))]
+const SCRIPT_SHELL: &str = "sh";
+#[cfg(windows)]
+const SCRIPT_SHELL: &str = "powershell.exe";
+
 pub struct ShellProcess {
+    master: Box<dyn MasterPty + Send>,
     child: Box<dyn Child + Send>,
+    /// Set once the child is reaped; its pid may be reused after that.
+    exit_status: Option<ExitStatus>,
+    program: String,
     writer: Box<dyn Write + Send>,
     reader_thread: Option<JoinHandle<()>>,
-    pub output_buffer: Arc<Mutex<Vec<u8>>>,
+    output: Receiver<Vec<u8>>,
+    /// Whether the shell loaded Clay's OSC 133 hooks.
+    integrated: bool,
 }
 
 impl ShellProcess {
-    pub fn new(rows: u16, cols: u16) -> Result<Self> {
+    pub fn new(config: &ShellConfig, rows: u16, cols: u16, waker: Waker) -> Result<Self> {
+        let shell_program = match &config.program {
+            Some(program) => program.clone(),
+            None => match std::env::var("SHELL") {
+                Ok(shell) if !shell.is_empty() => shell,
+                _ => Self::find_shell()?,
+            },
+        };
+        let mut cmd = Self::command(&shell_program)?;
+        let integrated = integration::install(
+            &mut cmd,
+            &shell_program,
+            &config.args,
+            config.init_file.as_deref(),
+        )?;
+        // Integration flags go first: bash rejects long options after short ones like `-l`.
+        cmd.args(&config.args);
+        for (key, value) in &config.env {
+            cmd.env(key, value);
+        }
+        let startup_input =
+            integration::startup_input(&shell_program, &config.args, config.init_file.as_deref());
+        let mut process = Self::spawn(cmd, shell_program, rows, cols, integrated, waker)?;
+        if let Some(input) = startup_input {
+            process.write_to_shell(input.as_bytes())?;
+        }
+        Ok(process)
+    }
+
+    pub fn run_command(command: &str, rows: u16, cols: u16, waker: Waker) -> Result<Self> {
+        let mut cmd = Self::command(SCRIPT_SHELL)?;
+        #[cfg(not(windows))]
+        cmd.args(["-c", command]);
+        #[cfg(windows)]
+        cmd.args(["-NoProfile", "-Command", command]);
+        Self::spawn(cmd, SCRIPT_SHELL.to_string(), rows, cols, false, waker)
+    }
+
+    fn command(program: &str) -> Result<CommandBuilder> {
+        let mut cmd = CommandBuilder::new(program);
+        cmd.cwd(std::env::current_dir()?);
+        cmd.env("TERM", "xterm-256color");
+        cmd.env("COLORTERM", "truecolor");
+        cmd.env("CLAY", "1");
+        Ok(cmd)
+    }
+
+    fn spawn(
+        cmd: CommandBuilder,
+        program: String,
+        rows: u16,
+        cols: u16,
+        integrated: bool,
+        waker: Waker,
+    ) -> Result<Self> {
         let pty_system = native_pty_system();
         let pair = pty_system.openpty(PtySize {
             rows,
@@ -23,14 +101,10 @@ impl ShellProcess {
             ..Default::default()
         })?;
 
-        let shell_program = Self::find_shell()?;
-        let mut cmd = CommandBuilder::new(shell_program);
-        cmd.cwd(std::env::current_dir()?);
         let child = pair.slave.spawn_command(cmd)?;
         let writer = pair.master.take_writer()?;
         let mut reader = pair.master.try_clone_reader()?;
-        let output_buffer = Arc::new(Mutex::new(Vec::new()));
-        let thread_buffer = output_buffer.clone();
+        let (sender, output) = mpsc::channel();
 
         let reader_thread = thread::spawn(move || {
             let mut buf = [0u8; 8192];
@@ -38,18 +112,26 @@ impl ShellProcess {
                 match reader.read(&mut buf) {
                     Ok(0) | Err(_) => break,
                     Ok(n) => {
-                        let mut buffer_lock = thread_buffer.lock().unwrap();
-                        buffer_lock.extend_from_slice(&buf[..n]);
+                        if sender.send(buf[..n].to_vec()).is_err() {
+                            break;
+                        }
+                        waker.wake();
                     }
                 }
             }
+            // Wake the loop once more so it notices that the child has exited.
+            waker.wake();
         });
 
         Ok(Self {
+            master: pair.master,
             child,
+            exit_status: None,
+            program,
             writer,
             reader_thread: Some(reader_thread),
-            output_buffer,
+            output,
+            integrated,
         })
     }
 
@@ -68,25 +150,98 @@ impl ShellProcess {
         Ok("powershell.exe".to_string())
     }
 
+    /// Shells without Clay's hooks get explicit OSC 133 marks around the command.
+    pub fn command_line(&self, command: &str) -> String {
+        if self.integrated {
+            format!("{}\n", command)
+        } else {
+            format!(
+                "{}{}\n{}\n",
+                FALLBACK_EXECUTED_MARK, command, FALLBACK_FINISHED_MARK
+            )
+        }
+    }
+
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// Input for a child that has exited is dropped.
     pub fn write_to_shell(&mut self, data: &[u8]) -> io::Result<()> {
-        self.writer.write_all(data)
+        match self.writer.write_all(data) {
+            Err(_) if matches!(self.try_wait(), Ok(Some(_))) => Ok(()),
+            result => result,
+        }
+    }
+
+    pub fn resize(&mut self, rows: u16, cols: u16) -> Result<()> {
+        self.master.resize(PtySize {
+            rows,
+            cols,
+            ..Default::default()
+        })?;
+        Ok(())
     }
 
+    /// Signals the PTY's foreground process group, and the child's own group if it differs.
+    #[cfg(unix)]
+    pub fn signal_foreground(&mut self, signal: i32) -> Result<()> {
+        let mut groups = Vec::new();
+        if let Some(pgrp) = self.master.process_group_leader() {
+            groups.push(pgrp);
+        }
+        if self.exit_status.is_none()
+            && let Some(pid) = self.child.process_id()
+        {
+            let pid = pid as libc::pid_t;
+            if !groups.contains(&pid) {
+                groups.push(pid);
+            }
+        }
+        for pgrp in groups {
+            if unsafe { libc::killpg(pgrp, signal) } != 0 {
+                let err = io::Error::last_os_error();
+                // The group may have exited in the meantime.
+                if err.raw_os_error() != Some(libc::ESRCH) {
+                    return Err(err.into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Windows has no signals, so every stage terminates the child.
+    #[cfg(windows)]
+    pub fn signal_foreground(&mut self, _signal: i32) -> Result<()> {
+        if self.exit_status.is_none() {
+            self.child.kill()?;
+        }
+        Ok(())
+    }
+
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        if self.exit_status.is_none() {
+            self.exit_status = self.child.try_wait()?;
+        }
+        Ok(self.exit_status.clone())
+    }
+
+    /// Chunks may end in the middle of a character or escape sequence.
     pub fn read_output_bytes(&self) -> Option<Vec<u8>> {
-        let mut buffer_lock = self.output_buffer.lock().unwrap();
-        if buffer_lock.is_empty() {
-            None
-        } else {
-            let output = buffer_lock.clone();
-            buffer_lock.clear();
-            Some(output)
+        let mut chunks = self.output.try_iter();
+        let mut output = chunks.next()?;
+        for chunk in chunks {
+            output.extend_from_slice(&chunk);
         }
+        Some(output)
     }
 }
 
 impl Drop for ShellProcess {
     fn drop(&mut self) {
-        let _ = self.child.kill();
+        if matches!(self.try_wait(), Ok(None)) {
+            let _ = self.child.kill();
+        }
         if let Some(handle) = self.reader_thread.take() {
             let _ = handle.join();
         }
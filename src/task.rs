@@ -0,0 +1,182 @@
+/* src/task.rs */
+
+use crate::app::ScriptEndStatus;
+use crate::event::Waker;
+use crate::shell::ShellProcess;
+use crate::terminal::VirtualTerminal;
+use anyhow::Result;
+use portable_pty::ExitStatus;
+use std::time::{Duration, Instant};
+
+const SIGINT: i32 = 2;
+const SIGKILL: i32 = 9;
+const SIGTERM: i32 = 15;
+
+#[derive(Clone, Copy)]
+pub struct CancelTimeouts {
+    pub term_after: Duration,
+    pub kill_after: Duration,
+}
+
+struct Cancellation {
+    signal: i32,
+    sent_at: Instant,
+    timeouts: CancelTimeouts,
+}
+
+#[derive(Clone)]
+pub struct TaskSpec {
+    pub name: String,
+    pub command: String,
+    pub status_message: String,
+    pub exclusive: bool,
+}
+
+pub struct Task {
+    pub name: String,
+    pub status_message: String,
+    pub exclusive: bool,
+    pub terminal: VirtualTerminal,
+    pub status: Option<ScriptEndStatus>,
+    process: ShellProcess,
+    cancellation: Option<Cancellation>,
+}
+
+impl Task {
+    pub fn spawn(
+        spec: &TaskSpec,
+        rows: u16,
+        cols: u16,
+        scrollback_lines: usize,
+        waker: Waker,
+    ) -> Result<Self> {
+        let process = ShellProcess::run_command(&spec.command, rows, cols, waker)?;
+        Ok(Self {
+            name: spec.name.clone(),
+            status_message: spec.status_message.clone(),
+            exclusive: spec.exclusive,
+            terminal: VirtualTerminal::new(rows, cols, scrollback_lines),
+            status: None,
+            process,
+            cancellation: None,
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status.is_none()
+    }
+
+    /// Returns the end status the first time the process is seen to have exited.
+    pub fn poll(&mut self) -> Result<Option<ScriptEndStatus>> {
+        self.terminal.flush_expired_sync_update();
+        if let Some(bytes) = self.process.read_output_bytes() {
+            self.terminal.process_bytes(&bytes);
+        }
+        if let Some(responses) = self.terminal.take_responses() {
+            // The process may already be gone, in which case nobody is waiting for a reply.
+            let _ = self.process.write_to_shell(&responses);
+        }
+        // Tasks may not set the host clipboard.
+        self.terminal.take_clipboard_writes();
+        self.terminal.take_shell_events();
+
+        if self.status.is_some() {
+            return Ok(None);
+        }
+        let Some(exit_status) = self.process.try_wait()? else {
+            self.escalate_cancellation()?;
+            return Ok(None);
+        };
+        let status = match (&self.cancellation, end_status(&exit_status)) {
+            (Some(_), ScriptEndStatus::Signalled(signal)) => ScriptEndStatus::Cancelled(signal),
+            // The task caught the signal and exited on its own.
+            (Some(cancellation), _) => ScriptEndStatus::Cancelled(cancellation.signal),
+            (None, status) => status,
+        };
+        self.status = Some(status);
+        Ok(Some(status))
+    }
+
+    pub fn cancel_signal(&self) -> Option<i32> {
+        self.cancellation.as_ref().map(|c| c.signal)
+    }
+
+    /// Sends SIGINT, or the next signal if the task is already being cancelled.
+    pub fn cancel(&mut self, timeouts: CancelTimeouts) -> Result<i32> {
+        let signal = match self.cancel_signal() {
+            None => SIGINT,
+            Some(SIGINT) => SIGTERM,
+            Some(_) => SIGKILL,
+        };
+        self.send_cancel_signal(signal, timeouts)?;
+        Ok(signal)
+    }
+
+    fn escalate_cancellation(&mut self) -> Result<()> {
+        let Some(cancellation) = &self.cancellation else {
+            return Ok(());
+        };
+        let (timeout, next_signal) = match cancellation.signal {
+            SIGINT => (cancellation.timeouts.term_after, SIGTERM),
+            SIGTERM => (cancellation.timeouts.kill_after, SIGKILL),
+            _ => return Ok(()),
+        };
+        if cancellation.sent_at.elapsed() >= timeout {
+            let timeouts = cancellation.timeouts;
+            self.send_cancel_signal(next_signal, timeouts)?;
+        }
+        Ok(())
+    }
+
+    fn send_cancel_signal(&mut self, signal: i32, timeouts: CancelTimeouts) -> Result<()> {
+        // Recorded even when delivery fails, so escalation moves on instead of retrying.
+        self.cancellation = Some(Cancellation {
+            signal,
+            sent_at: Instant::now(),
+            timeouts,
+        });
+        self.process.signal_foreground(signal)
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        if self.is_running() {
+            self.process.write_to_shell(data)?;
+        }
+        Ok(())
+    }
+
+    pub fn resize(&mut self, rows: u16, cols: u16) -> Result<()> {
+        self.terminal.resize(rows, cols);
+        self.process.resize(rows.max(1), cols.max(1))
+    }
+}
+
+/// portable-pty only reports the description of a fatal signal, so common ones are mapped
+/// back to signal numbers.
+pub fn end_status(exit_status: &ExitStatus) -> ScriptEndStatus {
+    let Some(description) = exit_status.signal() else {
+        return ScriptEndStatus::from_exit_code(Some(exit_status.exit_code() as i32));
+    };
+    // macOS appends the number itself, as in "Terminated: 15", and unknown signals are
+    // reported as "Signal 42".
+    if let Some(number) = description
+        .rsplit_once(": ")
+        .map(|(_, number)| number)
+        .or_else(|| description.strip_prefix("Signal "))
+        .and_then(|number| number.parse().ok())
+    {
+        return ScriptEndStatus::Signalled(number);
+    }
+    let signal = match description {
+        "Hangup" => 1,
+        "Interrupt" => 2,
+        "Quit" => 3,
+        "Aborted" => 6,
+        "Killed" => 9,
+        "Segmentation fault" => 11,
+        "Broken pipe" => 13,
+        "Terminated" => 15,
+        _ => return ScriptEndStatus::Failed(exit_status.exit_code() as i32),
+    };
+    ScriptEndStatus::Signalled(signal)
+}
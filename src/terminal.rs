@@ -2,57 +2,234 @@
 
 use ratatui::style::{Color, Modifier, Style};
 use ratatui::text::{Line, Span};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthChar;
 use vte::{Parser, Perform};
 
-const SCROLLBACK_BUFFER_SIZE: usize = 500;
-
 #[derive(Clone, Debug)]
 pub struct Cell {
     pub c: char,
+    pub combining: Vec<char>,
     pub fg: Color,
     pub bg: Color,
+    pub underline_color: Color,
     pub flags: CellFlags,
+    pub hyperlink: Option<Arc<str>>,
+}
+
+impl Cell {
+    pub fn is_blank(&self) -> bool {
+        self.c == ' '
+            && self.combining.is_empty()
+            && self.bg == Color::Reset
+            && self.flags.is_empty()
+            && self.hyperlink.is_none()
+    }
 }
 
 impl Default for Cell {
     fn default() -> Self {
         Self {
             c: ' ',
+            combining: Vec::new(),
             fg: Color::Reset,
             bg: Color::Reset,
+            underline_color: Color::Reset,
             flags: CellFlags::empty(),
+            hyperlink: None,
         }
     }
 }
 
 bitflags::bitflags! {
-    #[derive(Clone, Debug)]
-    pub struct CellFlags: u8 {
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct CellFlags: u32 {
         const BOLD = 1;
         const ITALIC = 2;
         const UNDERLINE = 4;
         const INVERSE = 8;
+        const WIDE_CHAR = 16;
+        /// Second column of a double-width character.
+        const WIDE_SPACER = 32;
+        /// Blank left at the end of a row when a wide character wrapped.
+        const WIDE_PADDING = 64;
+        const DIM = 128;
+        const BLINK = 256;
+        const RAPID_BLINK = 512;
+        const HIDDEN = 1024;
+        const STRIKETHROUGH = 2048;
+        const DOUBLE_UNDERLINE = 4096;
+        const CURLY_UNDERLINE = 8192;
+        const DOTTED_UNDERLINE = 16384;
+        const DASHED_UNDERLINE = 32768;
+        const OVERLINE = 65536;
+        const ANY_UNDERLINE = Self::UNDERLINE.bits()
+            | Self::DOUBLE_UNDERLINE.bits()
+            | Self::CURLY_UNDERLINE.bits()
+            | Self::DOTTED_UNDERLINE.bits()
+            | Self::DASHED_UNDERLINE.bits();
+    }
+}
+
+bitflags::bitflags! {
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct TermMode: u16 {
+        const APP_CURSOR = 1;
+        const MOUSE_CLICK = 2;
+        const MOUSE_DRAG = 4;
+        const MOUSE_MOTION = 8;
+        const MOUSE_SGR = 16;
+        const BRACKETED_PASTE = 32;
+        const SYNC_OUTPUT = 64;
+        const MOUSE_REPORTING =
+            Self::MOUSE_CLICK.bits() | Self::MOUSE_DRAG.bits() | Self::MOUSE_MOTION.bits();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShellEvent {
+    PromptStart,
+    CommandStart,
+    CommandExecuted,
+    CommandFinished(Option<i32>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Row {
+    pub cells: Vec<Cell>,
+    /// Set when the text continues on the next row.
+    pub wrapped: bool,
+    /// Rendered line, or `None` while the row is dirty.
+    line: Option<Line<'static>>,
+}
+
+impl Row {
+    pub fn new(cols: usize) -> Self {
+        Self::filled(cols, &Cell::default())
+    }
+
+    pub fn filled(cols: usize, blank: &Cell) -> Self {
+        Self {
+            cells: vec![blank.clone(); cols],
+            wrapped: false,
+            line: None,
+        }
+    }
+
+    pub fn clear(&mut self, blank: &Cell) {
+        for cell in &mut self.cells {
+            *cell = blank.clone();
+        }
+        self.wrapped = false;
+        self.line = None;
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.line.is_none()
+    }
+
+    pub fn mark_dirty(&mut self) {
+        self.line = None;
     }
+
+    fn render(&mut self) {
+        if self.is_dirty() {
+            self.line = Some(render_cells(&self.cells));
+        }
+    }
+
+    fn to_line(&self) -> Line<'_> {
+        match &self.line {
+            Some(line) => Line::from(
+                line.spans
+                    .iter()
+                    .map(|span| Span::styled(span.content.as_ref(), span.style))
+                    .collect::<Vec<_>>(),
+            ),
+            None => render_cells(&self.cells),
+        }
+    }
+}
+
+fn render_cells(cells: &[Cell]) -> Line<'static> {
+    let mut spans: Vec<Span> = Vec::new();
+    for cell in cells {
+        // The wide character itself already spans both columns when rendered.
+        if cell.flags.contains(CellFlags::WIDE_SPACER) {
+            continue;
+        }
+        let style = cell_to_ratatui_style(cell);
+        if !spans.last().is_some_and(|last| last.style == style) {
+            spans.push(Span::styled(String::new(), style));
+        }
+        if let Some(last) = spans.last_mut() {
+            let content = last.content.to_mut();
+            content.push(cell.c);
+            content.extend(cell.combining.iter());
+        }
+    }
+    Line::from(spans)
+}
+
+fn cell_to_ratatui_style(cell: &Cell) -> Style {
+    let mut style = Style::default();
+    style = style.fg(cell.fg);
+    style = style.bg(cell.bg);
+    let modifiers = [
+        (CellFlags::BOLD, Modifier::BOLD),
+        (CellFlags::DIM, Modifier::DIM),
+        (CellFlags::ITALIC, Modifier::ITALIC),
+        (CellFlags::BLINK, Modifier::SLOW_BLINK),
+        (CellFlags::RAPID_BLINK, Modifier::RAPID_BLINK),
+        (CellFlags::INVERSE, Modifier::REVERSED),
+        (CellFlags::HIDDEN, Modifier::HIDDEN),
+        (CellFlags::STRIKETHROUGH, Modifier::CROSSED_OUT),
+    ];
+    for (flag, modifier) in modifiers {
+        if cell.flags.contains(flag) {
+            style = style.add_modifier(modifier);
+        }
+    }
+    // ratatui has a single underline style and no overline. Hyperlinks are underlined.
+    if cell.flags.intersects(CellFlags::ANY_UNDERLINE) || cell.hyperlink.is_some() {
+        style = style.add_modifier(Modifier::UNDERLINED);
+        if cell.underline_color != Color::Reset {
+            style = style.underline_color(cell.underline_color);
+        }
+    }
+    style
 }
 
 pub struct Grid {
-    cells: Vec<Vec<Cell>>,
+    lines: Vec<Row>,
     rows: usize,
     cols: usize,
 }
 
 impl Grid {
     pub fn new(rows: usize, cols: usize) -> Self {
-        let cells = vec![vec![Cell::default(); cols]; rows];
-        Self { cells, rows, cols }
+        let lines = vec![Row::new(cols); rows];
+        Self { lines, rows, cols }
     }
 
     pub fn cell_mut(&mut self, row: usize, col: usize) -> Option<&mut Cell> {
-        self.cells.get_mut(row)?.get_mut(col)
+        let line = self.lines.get_mut(row)?;
+        line.mark_dirty();
+        line.cells.get_mut(col)
     }
 
     pub fn row(&self, row: usize) -> Option<&[Cell]> {
-        self.cells.get(row).map(|r| r.as_slice())
+        self.lines.get(row).map(|r| r.cells.as_slice())
+    }
+
+    pub fn line(&self, row: usize) -> Option<&Row> {
+        self.lines.get(row)
+    }
+
+    pub fn line_mut(&mut self, row: usize) -> Option<&mut Row> {
+        self.lines.get_mut(row)
     }
 
     pub fn height(&self) -> usize {
@@ -63,129 +240,853 @@ impl Grid {
         self.cols
     }
 
-    pub fn clear_line(&mut self, row: usize) {
-        if let Some(line) = self.cells.get_mut(row) {
-            for cell in line {
-                *cell = Cell::default();
+    pub fn set_wrapped(&mut self, row: usize, wrapped: bool) {
+        if let Some(line) = self.lines.get_mut(row) {
+            line.wrapped = wrapped;
+        }
+    }
+
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        for line in &mut self.lines {
+            line.cells.resize(cols, Cell::default());
+            line.mark_dirty();
+            // A wide character whose spacer was cut off can no longer be drawn.
+            if let Some(last) = line.cells.last_mut()
+                && last.flags.contains(CellFlags::WIDE_CHAR)
+            {
+                *last = Cell::default();
             }
         }
+        self.lines.resize(rows, Row::new(cols));
+        self.rows = rows;
+        self.cols = cols;
+    }
+
+    pub fn used_rows(&self) -> usize {
+        self.lines
+            .iter()
+            .rposition(|row| row.wrapped || !row.cells.iter().all(Cell::is_blank))
+            .map_or(0, |index| index + 1)
     }
 
-    pub fn clear_all(&mut self) {
-        for row in &mut self.cells {
-            for cell in row {
-                *cell = Cell::default();
+    pub fn take_rows(&mut self) -> Vec<Row> {
+        std::mem::take(&mut self.lines)
+    }
+
+    pub fn replace_rows(&mut self, mut lines: Vec<Row>, rows: usize, cols: usize) {
+        lines.truncate(rows);
+        for line in &mut lines {
+            line.cells.resize(cols, Cell::default());
+            line.mark_dirty();
+        }
+        lines.resize(rows, Row::new(cols));
+        self.lines = lines;
+        self.rows = rows;
+        self.cols = cols;
+    }
+
+    pub fn shift_out_top(&mut self, bottom: usize, blank: Row) -> Row {
+        let bottom = bottom.min(self.rows - 1);
+        let top_row = std::mem::replace(&mut self.lines[0], blank);
+        self.lines[..=bottom].rotate_left(1);
+        top_row
+    }
+
+    pub fn clear_line(&mut self, row: usize, blank: &Cell) {
+        if let Some(line) = self.lines.get_mut(row) {
+            line.clear(blank);
+        }
+    }
+
+    pub fn clear_all(&mut self, blank: &Cell) {
+        for line in &mut self.lines {
+            line.clear(blank);
+        }
+    }
+
+    /// Blanks the other half of a wide character at `col`.
+    pub fn split_wide_char(&mut self, row: usize, col: usize) {
+        let Some(line) = self.lines.get_mut(row) else {
+            return;
+        };
+        line.mark_dirty();
+        let Some(cell) = line.cells.get(col) else {
+            return;
+        };
+        if cell.flags.contains(CellFlags::WIDE_SPACER) && col > 0 {
+            line.cells[col - 1] = Cell::default();
+        } else if cell.flags.contains(CellFlags::WIDE_CHAR) && col + 1 < line.cells.len() {
+            line.cells[col + 1] = Cell::default();
+        }
+    }
+
+    pub fn insert_cells(&mut self, row: usize, col: usize, count: usize, blank: &Cell) {
+        if let Some(line) = self.lines.get_mut(row) {
+            line.mark_dirty();
+            let cells = &mut line.cells;
+            if col >= cells.len() {
+                return;
+            }
+            let count = count.min(cells.len() - col);
+            cells[col..].rotate_right(count);
+            for cell in &mut cells[col..col + count] {
+                *cell = blank.clone();
+            }
+        }
+    }
+
+    pub fn delete_cells(&mut self, row: usize, col: usize, count: usize, blank: &Cell) {
+        if let Some(line) = self.lines.get_mut(row) {
+            line.mark_dirty();
+            let cells = &mut line.cells;
+            if col >= cells.len() {
+                return;
             }
+            let count = count.min(cells.len() - col);
+            cells[col..].rotate_left(count);
+            let len = cells.len();
+            for cell in &mut cells[len - count..] {
+                *cell = blank.clone();
+            }
+        }
+    }
+
+    pub fn erase_cells(&mut self, row: usize, col: usize, count: usize, blank: &Cell) {
+        if let Some(line) = self.lines.get_mut(row) {
+            line.mark_dirty();
+            let end = col.saturating_add(count).min(line.cells.len());
+            for cell in line.cells.iter_mut().take(end).skip(col) {
+                *cell = blank.clone();
+            }
+        }
+    }
+
+    pub fn scroll_region_up(&mut self, top: usize, bottom: usize, lines: usize, blank: &Cell) {
+        let bottom = bottom.min(self.rows.saturating_sub(1));
+        if top > bottom || self.rows == 0 {
+            return;
+        }
+        let lines = lines.min(bottom - top + 1);
+        self.lines[top..=bottom].rotate_left(lines);
+        for row in (bottom + 1 - lines)..=bottom {
+            self.clear_line(row, blank);
+        }
+    }
+
+    pub fn scroll_region_down(&mut self, top: usize, bottom: usize, lines: usize, blank: &Cell) {
+        let bottom = bottom.min(self.rows.saturating_sub(1));
+        if top > bottom || self.rows == 0 {
+            return;
+        }
+        let lines = lines.min(bottom - top + 1);
+        self.lines[top..=bottom].rotate_right(lines);
+        for row in top..(top + lines) {
+            self.clear_line(row, blank);
+        }
+    }
+}
+
+pub struct Scrollback {
+    lines: VecDeque<Row>,
+    limit: usize,
+    /// Rows pushed so far, including evicted ones.
+    pushed: usize,
+}
+
+impl Scrollback {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            limit,
+            pushed: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn line(&self, index: usize) -> Option<&Row> {
+        self.lines.get(index)
+    }
+
+    pub fn line_mut(&mut self, index: usize) -> Option<&mut Row> {
+        self.lines.get_mut(index)
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    pub fn push(&mut self, row: Row) {
+        if self.limit == 0 {
+            return;
         }
+        self.pushed = self.pushed.wrapping_add(1);
+        while self.lines.len() >= self.limit {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(row);
+    }
+
+    /// Reuses the oldest row when it is about to be evicted anyway.
+    pub fn take_blank_row(&mut self, cols: usize, blank: &Cell) -> Row {
+        if self.limit > 0
+            && self.lines.len() >= self.limit
+            && let Some(mut row) = self.lines.pop_front()
+        {
+            row.clear(blank);
+            row.cells.resize(cols, blank.clone());
+            return row;
+        }
+        Row::filled(cols, blank)
+    }
+
+    fn take_all(&mut self) -> Vec<Row> {
+        self.lines.drain(..).collect()
     }
+}
+
+/// Resizes the primary screen and its scrollback, moving `cursors` along. The first one
+/// stays on screen.
+fn resize_primary(
+    grid: &mut Grid,
+    scrollback: &mut Scrollback,
+    screen_rows: usize,
+    cols: usize,
+    cursors: &mut [(usize, usize)],
+) {
+    let cursor_rows = cursors.iter().map(|cursor| cursor.0 + 1).max().unwrap_or(0);
+    let used_rows = grid.used_rows().max(cursor_rows);
+    let history_rows = scrollback.len();
+    let mut rows = scrollback.take_all();
+    let mut screen = grid.take_rows();
+    screen.truncate(used_rows);
+    rows.append(&mut screen);
 
-    pub fn scroll_up(&mut self, lines: usize) {
-        for _ in 0..lines {
-            self.cells.rotate_left(1);
-            if let Some(last_row) = self.cells.last_mut() {
-                for cell in last_row {
-                    *cell = Cell::default();
+    for cursor in cursors.iter_mut() {
+        cursor.0 += history_rows;
+    }
+    if cols != grid.width() {
+        rows = reflow_rows(rows, cols, cursors);
+    }
+
+    let anchor = cursors.first().map_or(usize::MAX, |cursor| cursor.0);
+    let screen_start = rows.len().saturating_sub(screen_rows).min(anchor);
+    let screen_lines = rows.split_off(screen_start);
+    for row in rows {
+        scrollback.push(row);
+    }
+    grid.replace_rows(screen_lines, screen_rows, cols);
+    for cursor in cursors.iter_mut() {
+        cursor.0 = cursor.0.saturating_sub(screen_start);
+    }
+}
+
+/// Trailing blanks are dropped so that shrinking and growing round-trips.
+fn reflow_rows(rows: Vec<Row>, cols: usize, cursors: &mut [(usize, usize)]) -> Vec<Row> {
+    let mut output: Vec<Row> = Vec::with_capacity(rows.len());
+    let mut new_cursors = cursors.to_vec();
+    let mut logical: Vec<Cell> = Vec::new();
+    let mut offsets = vec![None; cursors.len()];
+    let row_count = rows.len();
+
+    for (index, row) in rows.into_iter().enumerate() {
+        for (offset, cursor) in offsets.iter_mut().zip(cursors.iter()) {
+            if cursor.0 == index {
+                *offset = Some(logical.len() + cursor.1);
+            }
+        }
+        let wrapped = row.wrapped;
+        logical.extend(
+            row.cells
+                .into_iter()
+                .filter(|cell| !cell.flags.contains(CellFlags::WIDE_PADDING)),
+        );
+        if !wrapped || index + 1 == row_count {
+            let positions = rewrap_line(&mut output, std::mem::take(&mut logical), cols, &offsets);
+            for (new_cursor, position) in new_cursors.iter_mut().zip(positions) {
+                if let Some(position) = position {
+                    *new_cursor = position;
                 }
             }
+            offsets.fill(None);
+        }
+    }
+
+    for (new_cursor, cursor) in new_cursors.iter_mut().zip(cursors.iter()) {
+        if cursor.0 >= row_count {
+            *new_cursor = (output.len() + cursor.0 - row_count, cursor.1.min(cols));
+        }
+    }
+    cursors.copy_from_slice(&new_cursors);
+    output
+}
+
+fn rewrap_line(
+    output: &mut Vec<Row>,
+    mut cells: Vec<Cell>,
+    cols: usize,
+    offsets: &[Option<usize>],
+) -> Vec<Option<(usize, usize)>> {
+    let keep = offsets.iter().flatten().copied().max().unwrap_or(0);
+    while cells.len() > keep && cells.last().is_some_and(Cell::is_blank) {
+        cells.pop();
+    }
+
+    let mut current = Row::new(0);
+    let mut positions = vec![None; offsets.len()];
+    for (offset, cell) in cells.into_iter().enumerate() {
+        let needs_wrap = current.cells.len() == cols
+            || (cell.flags.contains(CellFlags::WIDE_CHAR) && current.cells.len() + 1 == cols);
+        if needs_wrap {
+            if current.cells.len() < cols {
+                let padding = Cell {
+                    flags: CellFlags::WIDE_PADDING,
+                    ..Cell::default()
+                };
+                current.cells.resize(cols, padding);
+            }
+            current.wrapped = true;
+            output.push(std::mem::replace(&mut current, Row::new(0)));
+        }
+        for (position, target) in positions.iter_mut().zip(offsets) {
+            if *target == Some(offset) {
+                *position = Some((output.len(), current.cells.len()));
+            }
+        }
+        current.cells.push(cell);
+    }
+
+    // Offsets right after the last cell of the line.
+    for (position, target) in positions.iter_mut().zip(offsets) {
+        if position.is_none() && target.is_some() {
+            *position = Some((output.len(), current.cells.len()));
         }
     }
+
+    current.cells.resize(cols, Cell::default());
+    output.push(current);
+    positions
+}
+
+#[derive(Clone, Copy, Default)]
+struct Pen {
+    fg: Color,
+    bg: Color,
+    underline_color: Color,
+    flags: CellFlags,
+}
+
+#[derive(Clone, Copy, Default)]
+struct SavedCursor {
+    row: usize,
+    col: usize,
+    pen: Pen,
 }
 
 pub struct TerminalState {
     grid: Grid,
+    /// Holds the primary grid while the alternate screen is shown.
+    inactive_grid: Grid,
+    alt_screen_active: bool,
+    mode: TermMode,
+    scrollback: Scrollback,
     cursor_row: usize,
     cursor_col: usize,
-    content_bottom_row: usize,
-    current_style: Style,
-    saved_cursor: (usize, usize),
+    pen: Pen,
+    saved_cursor: SavedCursor,
+    /// Each screen keeps its own DECSC state.
+    inactive_saved_cursor: SavedCursor,
+    scroll_region: Option<(usize, usize)>,
+    last_char: Option<char>,
+    responses: Vec<u8>,
+    title: Option<String>,
+    hyperlink: Option<Arc<str>>,
+    clipboard_writes: Vec<u8>,
+    shell_events: Vec<ShellEvent>,
+    /// Stops the parser right after DECSET 2026.
+    sync_requested: bool,
 }
 
 impl TerminalState {
-    pub fn new(rows: usize, cols: usize) -> Self {
+    pub fn new(rows: usize, cols: usize, scrollback_lines: usize) -> Self {
         Self {
             grid: Grid::new(rows, cols),
+            inactive_grid: Grid::new(rows, cols),
+            alt_screen_active: false,
+            mode: TermMode::default(),
+            scrollback: Scrollback::new(scrollback_lines),
             cursor_row: 0,
             cursor_col: 0,
-            content_bottom_row: 0,
-            current_style: Style::default(),
-            saved_cursor: (0, 0),
+            pen: Pen::default(),
+            saved_cursor: SavedCursor::default(),
+            inactive_saved_cursor: SavedCursor::default(),
+            scroll_region: None,
+            last_char: None,
+            responses: Vec::new(),
+            title: None,
+            hyperlink: None,
+            clipboard_writes: Vec::new(),
+            shell_events: Vec::new(),
+            sync_requested: false,
         }
     }
 
-    fn update_content_bottom(&mut self) {
-        self.content_bottom_row = self.content_bottom_row.max(self.cursor_row);
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        if self.alt_screen_active {
+            // The alternate screen is redrawn by its program after SIGWINCH, not reflowed.
+            let saved = &mut self.inactive_saved_cursor;
+            let mut cursors = [(saved.row, saved.col)];
+            resize_primary(
+                &mut self.inactive_grid,
+                &mut self.scrollback,
+                rows,
+                cols,
+                &mut cursors,
+            );
+            (saved.row, saved.col) = cursors[0];
+            self.grid.resize(rows, cols);
+        } else {
+            let saved = &mut self.saved_cursor;
+            let mut cursors = [(self.cursor_row, self.cursor_col), (saved.row, saved.col)];
+            resize_primary(
+                &mut self.grid,
+                &mut self.scrollback,
+                rows,
+                cols,
+                &mut cursors,
+            );
+            (self.cursor_row, self.cursor_col) = cursors[0];
+            (saved.row, saved.col) = cursors[1];
+            self.inactive_grid.resize(rows, cols);
+        }
+
+        self.scroll_region = None;
+        self.cursor_row = self.cursor_row.min(rows - 1);
+        self.cursor_col = self.cursor_col.min(cols - 1);
     }
 
-    fn write_char(&mut self, c: char) {
-        if self.cursor_col >= self.grid.width() {
+    fn enter_alt_screen(&mut self, clear: bool) {
+        if self.alt_screen_active {
+            return;
+        }
+        std::mem::swap(&mut self.grid, &mut self.inactive_grid);
+        std::mem::swap(&mut self.saved_cursor, &mut self.inactive_saved_cursor);
+        self.alt_screen_active = true;
+        if clear {
+            self.grid.clear_all(&Cell::default());
+        }
+    }
+
+    fn leave_alt_screen(&mut self, clear: bool) {
+        if !self.alt_screen_active {
+            return;
+        }
+        if clear {
+            self.grid.clear_all(&Cell::default());
+        }
+        std::mem::swap(&mut self.grid, &mut self.inactive_grid);
+        std::mem::swap(&mut self.saved_cursor, &mut self.inactive_saved_cursor);
+        self.alt_screen_active = false;
+    }
+
+    fn set_private_mode(&mut self, mode: u16, enabled: bool) {
+        if let Some(flag) = private_mode_flag(mode) {
+            self.mode.set(flag, enabled);
+            self.sync_requested |= flag == TermMode::SYNC_OUTPUT && enabled;
+            return;
+        }
+        match (mode, enabled) {
+            (47, true) => self.enter_alt_screen(false),
+            (47, false) => self.leave_alt_screen(false),
+            (1047, true) => self.enter_alt_screen(false),
+            (1047, false) => self.leave_alt_screen(true),
+            (1049, true) if !self.alt_screen_active => {
+                self.save_cursor();
+                self.enter_alt_screen(true);
+            }
+            (1049, false) if self.alt_screen_active => {
+                self.leave_alt_screen(false);
+                self.restore_cursor();
+            }
+            _ => {}
+        }
+    }
+
+    /// 1 set, 2 reset, 0 not recognized.
+    fn private_mode_state(&self, mode: u16) -> u8 {
+        let enabled = match mode {
+            47 | 1047 | 1049 => self.alt_screen_active,
+            _ => match private_mode_flag(mode) {
+                Some(flag) => self.mode.contains(flag),
+                None => return 0,
+            },
+        };
+        if enabled { 1 } else { 2 }
+    }
+
+    fn respond(&mut self, reply: &str) {
+        self.responses.extend_from_slice(reply.as_bytes());
+    }
+
+    fn report_cursor_position(&mut self, private: bool) {
+        let row = self.cursor_row + 1;
+        let col = self.cursor_col.min(self.grid.width() - 1) + 1;
+        let marker = if private { "?" } else { "" };
+        self.respond(&format!("\x1b[{}{};{}R", marker, row, col));
+    }
+
+    /// Erased cells keep the current background color (bce).
+    fn blank_cell(&self) -> Cell {
+        Cell {
+            bg: self.pen.bg,
+            ..Cell::default()
+        }
+    }
+
+    fn move_cursor_to(&mut self, row: usize, col: usize) {
+        self.cursor_row = row.min(self.grid.height() - 1);
+        self.cursor_col = col.min(self.grid.width() - 1);
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = SavedCursor {
+            row: self.cursor_row,
+            col: self.cursor_col,
+            pen: self.pen,
+        };
+    }
+
+    fn restore_cursor(&mut self) {
+        self.pen = self.saved_cursor.pen;
+        self.move_cursor_to(self.saved_cursor.row, self.saved_cursor.col);
+    }
+
+    fn scroll_region(&self) -> (usize, usize) {
+        let last_row = self.grid.height() - 1;
+        match self.scroll_region {
+            Some((top, bottom)) if bottom <= last_row => (top, bottom),
+            _ => (0, last_row),
+        }
+    }
+
+    fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        let last_row = self.grid.height() - 1;
+        let bottom = bottom.min(last_row);
+        if top >= bottom {
+            return;
+        }
+        self.scroll_region = if top == 0 && bottom == last_row {
+            None
+        } else {
+            Some((top, bottom))
+        };
+        self.cursor_row = 0;
+        self.cursor_col = 0;
+    }
+
+    fn scroll_up(&mut self, top: usize, bottom: usize, lines: usize) {
+        let blank = self.blank_cell();
+        if top != 0 || self.alt_screen_active {
+            self.grid.scroll_region_up(top, bottom, lines, &blank);
+            return;
+        }
+        for _ in 0..lines.min(bottom + 1) {
+            let blank = self.scrollback.take_blank_row(self.grid.width(), &blank);
+            let row = self.grid.shift_out_top(bottom, blank);
+            self.scrollback.push(row);
+        }
+    }
+
+    fn linefeed(&mut self) {
+        let (top, bottom) = self.scroll_region();
+        if self.cursor_row == bottom {
+            self.scroll_up(top, bottom, 1);
+        } else if self.cursor_row + 1 < self.grid.height() {
             self.cursor_row += 1;
-            self.cursor_col = 0;
         }
+    }
 
-        if self.cursor_row >= self.grid.height() {
-            let scroll_count = self.cursor_row - self.grid.height() + 1;
-            self.grid.scroll_up(scroll_count);
-            self.cursor_row = self.grid.height() - 1;
+    fn reverse_index(&mut self) {
+        let (top, bottom) = self.scroll_region();
+        if self.cursor_row == top {
+            let blank = self.blank_cell();
+            self.grid.scroll_region_down(top, bottom, 1, &blank);
+        } else {
+            self.cursor_row = self.cursor_row.saturating_sub(1);
         }
+    }
 
-        self.update_content_bottom();
+    fn insert_lines(&mut self, count: usize) {
+        let (top, bottom) = self.scroll_region();
+        if self.cursor_row >= top && self.cursor_row <= bottom {
+            let blank = self.blank_cell();
+            self.grid
+                .scroll_region_down(self.cursor_row, bottom, count, &blank);
+            self.cursor_col = 0;
+        }
+    }
 
-        let fg_color = self.ratatui_style_to_color(self.current_style.fg);
-        let bg_color = self.ratatui_style_to_color(self.current_style.bg);
-        let mut flags = CellFlags::empty();
-        if self.current_style.add_modifier.contains(Modifier::BOLD) {
-            flags |= CellFlags::BOLD;
+    fn delete_lines(&mut self, count: usize) {
+        let (top, bottom) = self.scroll_region();
+        if self.cursor_row >= top && self.cursor_row <= bottom {
+            let blank = self.blank_cell();
+            self.grid
+                .scroll_region_up(self.cursor_row, bottom, count, &blank);
+            self.cursor_col = 0;
         }
-        if self.current_style.add_modifier.contains(Modifier::ITALIC) {
-            flags |= CellFlags::ITALIC;
+    }
+
+    fn write_char(&mut self, c: char) {
+        let width = c.width().unwrap_or(0);
+        if width == 0 {
+            self.append_combining(c);
+            return;
         }
-        if self
-            .current_style
-            .add_modifier
-            .contains(Modifier::UNDERLINED)
-        {
-            flags |= CellFlags::UNDERLINE;
+
+        let wrap_at = if width == 2 && self.grid.width() >= 2 {
+            self.grid.width() - 1
+        } else {
+            self.grid.width()
+        };
+        if self.cursor_col >= wrap_at {
+            if self.cursor_col < self.grid.width()
+                && let Some(cell) = self.grid.cell_mut(self.cursor_row, self.cursor_col)
+            {
+                *cell = Cell {
+                    flags: CellFlags::WIDE_PADDING,
+                    ..Cell::default()
+                };
+            }
+            self.grid.set_wrapped(self.cursor_row, true);
+            self.cursor_col = 0;
+            self.linefeed();
         }
-        if self.current_style.add_modifier.contains(Modifier::REVERSED) {
-            flags |= CellFlags::INVERSE;
+
+        let pen = self.pen;
+
+        self.grid.split_wide_char(self.cursor_row, self.cursor_col);
+        if width == 2 {
+            self.grid
+                .split_wide_char(self.cursor_row, self.cursor_col + 1);
         }
 
         if let Some(cell) = self.grid.cell_mut(self.cursor_row, self.cursor_col) {
             cell.c = c;
-            cell.fg = fg_color;
-            cell.bg = bg_color;
-            cell.flags = flags;
+            cell.combining.clear();
+            cell.fg = pen.fg;
+            cell.bg = pen.bg;
+            cell.underline_color = pen.underline_color;
+            cell.flags = pen.flags;
+            if width == 2 {
+                cell.flags |= CellFlags::WIDE_CHAR;
+            }
+            cell.hyperlink = self.hyperlink.clone();
+        }
+        if width == 2
+            && let Some(cell) = self.grid.cell_mut(self.cursor_row, self.cursor_col + 1)
+        {
+            cell.c = ' ';
+            cell.combining.clear();
+            cell.fg = pen.fg;
+            cell.bg = pen.bg;
+            cell.underline_color = pen.underline_color;
+            cell.flags = pen.flags | CellFlags::WIDE_SPACER;
+            cell.hyperlink = self.hyperlink.clone();
+        }
+        self.cursor_col += width.min(self.grid.width());
+    }
+
+    fn append_combining(&mut self, c: char) {
+        if self.cursor_col == 0 {
+            return;
+        }
+        let mut col = self.cursor_col.min(self.grid.width()) - 1;
+        let is_spacer = self
+            .grid
+            .row(self.cursor_row)
+            .and_then(|row| row.get(col))
+            .is_some_and(|cell| cell.flags.contains(CellFlags::WIDE_SPACER));
+        if is_spacer && col > 0 {
+            col -= 1;
         }
-        self.cursor_col += 1;
+        if let Some(cell) = self.grid.cell_mut(self.cursor_row, col) {
+            cell.combining.push(c);
+        }
+    }
+
+    fn apply_sgr(&mut self, params: &vte::Params) {
+        let mut iter = params.iter();
+        while let Some(param) = iter.next() {
+            let value = param.first().copied().unwrap_or(0);
+            let flags = &mut self.pen.flags;
+            match value {
+                0 => self.pen = Pen::default(),
+                1 => flags.insert(CellFlags::BOLD),
+                2 => flags.insert(CellFlags::DIM),
+                3 => flags.insert(CellFlags::ITALIC),
+                4 => {
+                    // The `4:n` sub-parameter picks the style; `4:0` turns underlining off.
+                    let underline = match param.get(1).copied().unwrap_or(1) {
+                        0 => CellFlags::empty(),
+                        2 => CellFlags::DOUBLE_UNDERLINE,
+                        3 => CellFlags::CURLY_UNDERLINE,
+                        4 => CellFlags::DOTTED_UNDERLINE,
+                        5 => CellFlags::DASHED_UNDERLINE,
+                        _ => CellFlags::UNDERLINE,
+                    };
+                    flags.remove(CellFlags::ANY_UNDERLINE);
+                    flags.insert(underline);
+                }
+                5 => flags.insert(CellFlags::BLINK),
+                6 => flags.insert(CellFlags::RAPID_BLINK),
+                7 => flags.insert(CellFlags::INVERSE),
+                8 => flags.insert(CellFlags::HIDDEN),
+                9 => flags.insert(CellFlags::STRIKETHROUGH),
+                21 => {
+                    flags.remove(CellFlags::ANY_UNDERLINE);
+                    flags.insert(CellFlags::DOUBLE_UNDERLINE);
+                }
+                22 => flags.remove(CellFlags::BOLD | CellFlags::DIM),
+                23 => flags.remove(CellFlags::ITALIC),
+                24 => flags.remove(CellFlags::ANY_UNDERLINE),
+                25 => flags.remove(CellFlags::BLINK | CellFlags::RAPID_BLINK),
+                27 => flags.remove(CellFlags::INVERSE),
+                28 => flags.remove(CellFlags::HIDDEN),
+                29 => flags.remove(CellFlags::STRIKETHROUGH),
+                30..=37 => self.pen.fg = ansi_color(value - 30),
+                38 => {
+                    if let Some(color) = parse_extended_color(param, &mut iter) {
+                        self.pen.fg = color;
+                    }
+                }
+                39 => self.pen.fg = Color::Reset,
+                40..=47 => self.pen.bg = ansi_color(value - 40),
+                48 => {
+                    if let Some(color) = parse_extended_color(param, &mut iter) {
+                        self.pen.bg = color;
+                    }
+                }
+                49 => self.pen.bg = Color::Reset,
+                53 => flags.insert(CellFlags::OVERLINE),
+                55 => flags.remove(CellFlags::OVERLINE),
+                58 => {
+                    if let Some(color) = parse_extended_color(param, &mut iter) {
+                        self.pen.underline_color = color;
+                    }
+                }
+                59 => self.pen.underline_color = Color::Reset,
+                90..=97 => self.pen.fg = ansi_color(value - 90 + 8),
+                100..=107 => self.pen.bg = ansi_color(value - 100 + 8),
+                _ => {}
+            }
+        }
+    }
+}
+
+fn csi_param(params: &vte::Params, index: usize, default: u16) -> u16 {
+    params
+        .iter()
+        .nth(index)
+        .and_then(|p| p.first().copied())
+        .filter(|&value| value != 0)
+        .unwrap_or(default)
+}
+
+fn private_mode_flag(mode: u16) -> Option<TermMode> {
+    let flag = match mode {
+        1 => TermMode::APP_CURSOR,
+        1000 => TermMode::MOUSE_CLICK,
+        1002 => TermMode::MOUSE_DRAG,
+        1003 => TermMode::MOUSE_MOTION,
+        1006 => TermMode::MOUSE_SGR,
+        2004 => TermMode::BRACKETED_PASTE,
+        2026 => TermMode::SYNC_OUTPUT,
+        _ => return None,
+    };
+    Some(flag)
+}
+
+fn ansi_color(index: u16) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        7 => Color::Gray,
+        8 => Color::DarkGray,
+        9 => Color::LightRed,
+        10 => Color::LightGreen,
+        11 => Color::LightYellow,
+        12 => Color::LightBlue,
+        13 => Color::LightMagenta,
+        14 => Color::LightCyan,
+        15 => Color::White,
+        _ => Color::Reset,
     }
+}
 
-    fn ratatui_style_to_color(&self, color: Option<Color>) -> Color {
-        color.unwrap_or(Color::Reset)
+/// Handles both `38:2::r:g:b` subparameters and the `38;2;r;g;b` form.
+fn parse_extended_color<'a>(
+    param: &[u16],
+    rest: &mut impl Iterator<Item = &'a [u16]>,
+) -> Option<Color> {
+    if param.len() > 1 {
+        return match param[1] {
+            5 => param.get(2).map(|&n| Color::Indexed(n as u8)),
+            2 => {
+                // An optional color space id sits between the mode and the components.
+                let components = if param.len() >= 6 {
+                    &param[3..6]
+                } else {
+                    param.get(2..5)?
+                };
+                Some(Color::Rgb(
+                    components[0] as u8,
+                    components[1] as u8,
+                    components[2] as u8,
+                ))
+            }
+            _ => None,
+        };
+    }
+
+    let mut next = || rest.next().and_then(|p| p.first().copied());
+    match next()? {
+        5 => next().map(|n| Color::Indexed(n as u8)),
+        2 => {
+            let r = next()?;
+            let g = next()?;
+            let b = next()?;
+            Some(Color::Rgb(r as u8, g as u8, b as u8))
+        }
+        _ => None,
     }
 }
 
 impl Perform for TerminalState {
+    fn terminated(&self) -> bool {
+        self.sync_requested
+    }
+
     fn print(&mut self, c: char) {
         self.write_char(c);
+        self.last_char = Some(c);
     }
 
     fn execute(&mut self, byte: u8) {
         match byte {
-            b'\n' => {
-                self.cursor_row += 1;
-                if self.cursor_row >= self.grid.height() {
-                    self.grid.scroll_up(1);
-                    self.cursor_row = self.grid.height() - 1;
-                }
-                self.update_content_bottom();
-            }
+            b'\n' | b'\x0b' | b'\x0c' => self.linefeed(),
             b'\r' => self.cursor_col = 0,
+            // HT moves to the next tab stop without writing.
             b'\t' => {
                 let tab_stop = 8;
-                let spaces = tab_stop - (self.cursor_col % tab_stop);
-                for _ in 0..spaces {
-                    self.write_char(' ');
-                }
+                let next_stop = (self.cursor_col / tab_stop + 1) * tab_stop;
+                self.cursor_col = next_stop.min(self.grid.width() - 1);
             }
             b'\x08' => {
                 if self.cursor_col > 0 {
@@ -199,297 +1100,744 @@ impl Perform for TerminalState {
     fn hook(&mut self, _params: &vte::Params, _intermediates: &[u8], _ignore: bool, _c: char) {}
     fn put(&mut self, _byte: u8) {}
     fn unhook(&mut self) {}
-    fn osc_dispatch(&mut self, _params: &[&[u8]], _bell_terminated: bool) {}
-    fn esc_dispatch(&mut self, _intermediates: &[u8], _ignore: bool, _byte: u8) {}
-
-    fn csi_dispatch(
-        &mut self,
-        params: &vte::Params,
-        _intermediates: &[u8],
-        _ignore: bool,
-        c: char,
-    ) {
+    fn osc_dispatch(&mut self, params: &[&[u8]], _bell_terminated: bool) {
+        match params {
+            [b"0" | b"2", title @ ..] => {
+                let title: String = String::from_utf8_lossy(&title.join(&b';'))
+                    .chars()
+                    .filter(|c| !c.is_control())
+                    .collect();
+                self.title = (!title.is_empty()).then_some(title);
+            }
+            // OSC 8 ; params ; URI. An empty URI closes the link.
+            [b"8", _, uri @ ..] => {
+                let uri = String::from_utf8_lossy(&uri.join(&b';')).into_owned();
+                self.hyperlink = (!uri.is_empty()).then(|| Arc::from(uri));
+            }
+            [b"133", mark, rest @ ..] => {
+                let event = match *mark {
+                    b"A" => ShellEvent::PromptStart,
+                    b"B" => ShellEvent::CommandStart,
+                    b"C" => ShellEvent::CommandExecuted,
+                    b"D" => {
+                        let exit_code = rest
+                            .first()
+                            .and_then(|code| std::str::from_utf8(code).ok())
+                            .and_then(|code| code.parse().ok());
+                        ShellEvent::CommandFinished(exit_code)
+                    }
+                    _ => return,
+                };
+                self.shell_events.push(event);
+            }
+            // OSC 52 ; selection ; base64 data. Queries ("?") would leak the host clipboard
+            // to the child, so only writes are forwarded.
+            [b"52", selection, data] if *data != b"?" => {
+                let is_base64 = data
+                    .iter()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'='));
+                if is_base64 {
+                    self.clipboard_writes.extend_from_slice(b"\x1b]52;");
+                    self.clipboard_writes.extend_from_slice(selection);
+                    self.clipboard_writes.push(b';');
+                    self.clipboard_writes.extend_from_slice(data);
+                    self.clipboard_writes.push(0x07);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], _ignore: bool, byte: u8) {
+        if !intermediates.is_empty() {
+            return;
+        }
+        match byte {
+            b'D' => self.linefeed(),
+            b'E' => {
+                self.cursor_col = 0;
+                self.linefeed();
+            }
+            b'M' => self.reverse_index(),
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &vte::Params, intermediates: &[u8], _ignore: bool, c: char) {
+        match (intermediates, c) {
+            (b"", _) => {}
+            (b"?", 'h' | 'l') => {
+                for param in params.iter() {
+                    if let Some(&mode) = param.first() {
+                        self.set_private_mode(mode, c == 'h');
+                    }
+                }
+                return;
+            }
+            // DECXCPR
+            (b"?", 'n') if csi_param(params, 0, 0) == 6 => {
+                self.report_cursor_position(true);
+                return;
+            }
+            // DA2: identify as a VT220-class terminal.
+            (b">", 'c') if csi_param(params, 0, 0) == 0 => {
+                self.respond("\x1b[>1;10;0c");
+                return;
+            }
+            // DECRQM for DEC private modes.
+            (b"?$", 'p') => {
+                let mode = csi_param(params, 0, 0);
+                let state = self.private_mode_state(mode);
+                self.respond(&format!("\x1b[?{};{}$y", mode, state));
+                return;
+            }
+            // DECRQM for ANSI modes, none of which are implemented.
+            (b"$", 'p') => {
+                let mode = csi_param(params, 0, 0);
+                self.respond(&format!("\x1b[{};0$y", mode));
+                return;
+            }
+            _ => return,
+        }
+
         match c {
             'A' => {
-                let lines = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&1);
-                self.cursor_row = self.cursor_row.saturating_sub(*lines as usize);
+                let row = self
+                    .cursor_row
+                    .saturating_sub(csi_param(params, 0, 1) as usize);
+                self.move_cursor_to(row, self.cursor_col);
             }
-            'B' => {
-                let lines = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&1);
-                let max_row = self.grid.height() - 1;
-                self.cursor_row = (self.cursor_row + *lines as usize).min(max_row);
-                self.update_content_bottom();
+            'B' | 'e' => {
+                let row = self.cursor_row + csi_param(params, 0, 1) as usize;
+                self.move_cursor_to(row, self.cursor_col);
             }
-            'C' => {
-                let cols = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&1);
-                let max_col = self.grid.width() - 1;
-                self.cursor_col = (self.cursor_col + *cols as usize).min(max_col);
+            'C' | 'a' => {
+                let col = self.cursor_col + csi_param(params, 0, 1) as usize;
+                self.move_cursor_to(self.cursor_row, col);
             }
             'D' => {
-                let cols = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&1);
-                self.cursor_col = self.cursor_col.saturating_sub(*cols as usize);
-            }
-            'H' => {
-                let mut iter = params.iter();
-                let row = iter
-                    .next()
-                    .and_then(|p| p.get(0))
-                    .unwrap_or(&1)
-                    .saturating_sub(1) as usize;
-                let col = iter
-                    .next()
-                    .and_then(|p| p.get(0))
-                    .unwrap_or(&1)
-                    .saturating_sub(1) as usize;
-                self.cursor_row = row.min(self.grid.height() - 1);
-                self.cursor_col = col.min(self.grid.width() - 1);
-                self.update_content_bottom();
+                let col = self
+                    .cursor_col
+                    .min(self.grid.width() - 1)
+                    .saturating_sub(csi_param(params, 0, 1) as usize);
+                self.move_cursor_to(self.cursor_row, col);
+            }
+            'E' => {
+                let row = self.cursor_row + csi_param(params, 0, 1) as usize;
+                self.move_cursor_to(row, 0);
+            }
+            'F' => {
+                let row = self
+                    .cursor_row
+                    .saturating_sub(csi_param(params, 0, 1) as usize);
+                self.move_cursor_to(row, 0);
+            }
+            'G' | '`' => {
+                let col = csi_param(params, 0, 1) as usize - 1;
+                self.move_cursor_to(self.cursor_row, col);
+            }
+            'd' => {
+                let row = csi_param(params, 0, 1) as usize - 1;
+                self.move_cursor_to(row, self.cursor_col);
+            }
+            'H' | 'f' => {
+                let row = csi_param(params, 0, 1) as usize - 1;
+                let col = csi_param(params, 1, 1) as usize - 1;
+                self.move_cursor_to(row, col);
             }
             'J' => {
+                let blank = self.blank_cell();
                 let mode = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&0);
                 match mode {
                     0 => {
                         for col in self.cursor_col..self.grid.width() {
                             if let Some(cell) = self.grid.cell_mut(self.cursor_row, col) {
-                                *cell = Cell::default();
+                                *cell = blank.clone();
                             }
                         }
                         for row in (self.cursor_row + 1)..self.grid.height() {
-                            self.grid.clear_line(row);
+                            self.grid.clear_line(row, &blank);
                         }
                     }
                     1 => {
                         for row in 0..self.cursor_row {
-                            self.grid.clear_line(row);
+                            self.grid.clear_line(row, &blank);
                         }
                         for col in 0..=self.cursor_col {
                             if let Some(cell) = self.grid.cell_mut(self.cursor_row, col) {
-                                *cell = Cell::default();
+                                *cell = blank.clone();
                             }
                         }
                     }
-                    2 => self.grid.clear_all(),
+                    2 => self.grid.clear_all(&blank),
+                    3 => self.scrollback.clear(),
                     _ => {}
                 }
             }
             'K' => {
+                let blank = self.blank_cell();
                 let mode = params.iter().next().and_then(|p| p.get(0)).unwrap_or(&0);
                 match mode {
                     0 => {
                         for col in self.cursor_col..self.grid.width() {
                             if let Some(cell) = self.grid.cell_mut(self.cursor_row, col) {
-                                *cell = Cell::default();
+                                *cell = blank.clone();
                             }
                         }
                     }
                     1 => {
                         for col in 0..=self.cursor_col {
                             if let Some(cell) = self.grid.cell_mut(self.cursor_row, col) {
-                                *cell = Cell::default();
+                                *cell = blank.clone();
                             }
                         }
                     }
-                    2 => self.grid.clear_line(self.cursor_row),
+                    2 => self.grid.clear_line(self.cursor_row, &blank),
                     _ => {}
                 }
             }
-            'm' => {
-                for param in params.iter() {
-                    for &value in param {
-                        match value {
-                            0 => self.current_style = Style::default(),
-                            1 => {
-                                self.current_style = self.current_style.add_modifier(Modifier::BOLD)
-                            }
-                            3 => {
-                                self.current_style =
-                                    self.current_style.add_modifier(Modifier::ITALIC)
-                            }
-                            4 => {
-                                self.current_style =
-                                    self.current_style.add_modifier(Modifier::UNDERLINED)
-                            }
-                            7 => {
-                                self.current_style =
-                                    self.current_style.add_modifier(Modifier::REVERSED)
-                            }
-                            22 => {
-                                self.current_style =
-                                    self.current_style.remove_modifier(Modifier::BOLD)
-                            }
-                            23 => {
-                                self.current_style =
-                                    self.current_style.remove_modifier(Modifier::ITALIC)
-                            }
-                            24 => {
-                                self.current_style =
-                                    self.current_style.remove_modifier(Modifier::UNDERLINED)
-                            }
-                            27 => {
-                                self.current_style =
-                                    self.current_style.remove_modifier(Modifier::REVERSED)
-                            }
-                            30..=37 => {
-                                let color = match value {
-                                    30 => Color::Black,
-                                    31 => Color::Red,
-                                    32 => Color::Green,
-                                    33 => Color::Yellow,
-                                    34 => Color::Blue,
-                                    35 => Color::Magenta,
-                                    36 => Color::Cyan,
-                                    37 => Color::White,
-                                    _ => Color::Reset,
-                                };
-                                self.current_style = self.current_style.fg(color);
-                            }
-                            40..=47 => {
-                                let color = match value {
-                                    40 => Color::Black,
-                                    41 => Color::Red,
-                                    42 => Color::Green,
-                                    43 => Color::Yellow,
-                                    44 => Color::Blue,
-                                    45 => Color::Magenta,
-                                    46 => Color::Cyan,
-                                    47 => Color::White,
-                                    _ => Color::Reset,
-                                };
-                                self.current_style = self.current_style.bg(color);
-                            }
-                            90..=97 => {
-                                let color = match value {
-                                    90 => Color::DarkGray,
-                                    91 => Color::LightRed,
-                                    92 => Color::LightGreen,
-                                    93 => Color::LightYellow,
-                                    94 => Color::LightBlue,
-                                    95 => Color::LightMagenta,
-                                    96 => Color::LightCyan,
-                                    97 => Color::White,
-                                    _ => Color::Reset,
-                                };
-                                self.current_style = self.current_style.fg(color);
-                            }
-                            _ => {}
-                        }
+            'm' => self.apply_sgr(params),
+            'r' => {
+                let top = csi_param(params, 0, 1) as usize - 1;
+                let bottom = csi_param(params, 1, self.grid.height() as u16) as usize - 1;
+                self.set_scroll_region(top, bottom);
+            }
+            '@' => {
+                let col = self.cursor_col.min(self.grid.width() - 1);
+                let blank = self.blank_cell();
+                self.grid.insert_cells(
+                    self.cursor_row,
+                    col,
+                    csi_param(params, 0, 1) as usize,
+                    &blank,
+                );
+            }
+            'P' => {
+                let col = self.cursor_col.min(self.grid.width() - 1);
+                let blank = self.blank_cell();
+                self.grid.delete_cells(
+                    self.cursor_row,
+                    col,
+                    csi_param(params, 0, 1) as usize,
+                    &blank,
+                );
+            }
+            'X' => {
+                let col = self.cursor_col.min(self.grid.width() - 1);
+                let blank = self.blank_cell();
+                self.grid.erase_cells(
+                    self.cursor_row,
+                    col,
+                    csi_param(params, 0, 1) as usize,
+                    &blank,
+                );
+            }
+            'b' => {
+                if let Some(c) = self.last_char {
+                    for _ in 0..csi_param(params, 0, 1) {
+                        self.write_char(c);
                     }
                 }
             }
-            's' => self.saved_cursor = (self.cursor_row, self.cursor_col),
-            'u' => {
-                self.cursor_row = self.saved_cursor.0;
-                self.cursor_col = self.saved_cursor.1;
-                self.update_content_bottom();
+            'L' => self.insert_lines(csi_param(params, 0, 1) as usize),
+            'M' => self.delete_lines(csi_param(params, 0, 1) as usize),
+            'S' => {
+                let (top, bottom) = self.scroll_region();
+                self.scroll_up(top, bottom, csi_param(params, 0, 1) as usize);
+            }
+            'T' => {
+                let (top, bottom) = self.scroll_region();
+                let blank = self.blank_cell();
+                self.grid
+                    .scroll_region_down(top, bottom, csi_param(params, 0, 1) as usize, &blank);
             }
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
+            // DA1: VT220 with ANSI color.
+            'c' if csi_param(params, 0, 0) == 0 => self.respond("\x1b[?62;22c"),
+            'n' => match csi_param(params, 0, 0) {
+                5 => self.respond("\x1b[0n"),
+                6 => self.report_cursor_position(false),
+                _ => {}
+            },
             _ => {}
         }
     }
 }
 
+const SYNC_UPDATE_TIMEOUT: Duration = Duration::from_millis(150);
+
+const SYNC_UPDATE_BUFFER_LIMIT: usize = 2 * 1024 * 1024;
+
+struct SyncUpdate {
+    started: Instant,
+    buffer: Vec<u8>,
+    /// Watches for DECRST 2026, which may be combined with other modes.
+    scanner: Parser,
+    end: SyncUpdateEnd,
+}
+
+impl SyncUpdate {
+    fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            buffer: Vec::new(),
+            scanner: Parser::new(),
+            end: SyncUpdateEnd(false),
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> bool {
+        self.buffer.extend_from_slice(bytes);
+        self.scanner.advance(&mut self.end, bytes);
+        self.end.0 || self.buffer.len() >= SYNC_UPDATE_BUFFER_LIMIT
+    }
+
+    fn deadline(&self) -> Instant {
+        self.started + SYNC_UPDATE_TIMEOUT
+    }
+}
+
+struct SyncUpdateEnd(bool);
+
+impl Perform for SyncUpdateEnd {
+    fn csi_dispatch(&mut self, params: &vte::Params, intermediates: &[u8], _ignore: bool, c: char) {
+        if intermediates == b"?" && c == 'l' {
+            self.0 |= params.iter().any(|param| param.first() == Some(&2026));
+        }
+    }
+}
+
 pub struct VirtualTerminal {
     state: TerminalState,
     parser: Parser,
     visible_rows: u16,
     scroll_offset: usize,
+    damaged: bool,
+    sync_update: Option<SyncUpdate>,
 }
 
 impl VirtualTerminal {
-    pub fn new(rows: u16, cols: u16) -> Self {
+    pub fn new(rows: u16, cols: u16, scrollback_lines: usize) -> Self {
+        let rows = rows.max(1);
+        let cols = cols.max(1);
         Self {
-            state: TerminalState::new(SCROLLBACK_BUFFER_SIZE, cols as usize),
+            state: TerminalState::new(rows as usize, cols as usize, scrollback_lines),
             parser: Parser::new(),
             visible_rows: rows,
             scroll_offset: 0,
+            damaged: true,
+            sync_update: None,
         }
     }
 
+    pub fn resize(&mut self, rows: u16, cols: u16) {
+        let rows = rows.max(1);
+        let cols = cols.max(1);
+        self.state.resize(rows as usize, cols as usize);
+        self.visible_rows = rows;
+        self.scroll_offset = 0;
+        self.damaged = true;
+    }
+
     pub fn clear(&mut self) {
-        self.state.grid.clear_all();
+        self.state.grid.clear_all(&Cell::default());
+        self.state.scrollback.clear();
         self.state.cursor_row = 0;
         self.state.cursor_col = 0;
-        self.state.content_bottom_row = 0;
         self.scroll_offset = 0;
+        self.damaged = true;
+    }
+
+    pub fn size(&self) -> (u16, u16) {
+        (
+            self.state.grid.height() as u16,
+            self.state.grid.width() as u16,
+        )
+    }
+
+    pub fn take_responses(&mut self) -> Option<Vec<u8>> {
+        if self.state.responses.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.state.responses))
+        }
     }
 
+    pub fn take_clipboard_writes(&mut self) -> Option<Vec<u8>> {
+        if self.state.clipboard_writes.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.state.clipboard_writes))
+        }
+    }
+
+    pub fn take_shell_events(&mut self) -> Vec<ShellEvent> {
+        std::mem::take(&mut self.state.shell_events)
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.state.title.as_deref()
+    }
+
+    pub fn mode(&self) -> TermMode {
+        self.state.mode
+    }
+
+    /// Output of a synchronized update is held back until it ends.
     pub fn process_bytes(&mut self, bytes: &[u8]) {
-        self.scroll_offset = 0;
-        self.parser.advance(&mut self.state, bytes);
+        match &mut self.sync_update {
+            Some(update) => {
+                if update.push(bytes) {
+                    self.apply_sync_update();
+                }
+            }
+            None => self.parse(bytes),
+        }
+    }
+
+    pub fn flush_expired_sync_update(&mut self) {
+        if self
+            .sync_update
+            .as_ref()
+            .is_some_and(|update| Instant::now() >= update.deadline())
+        {
+            self.apply_sync_update();
+        }
+    }
+
+    pub fn sync_update_deadline(&self) -> Option<Instant> {
+        self.sync_update.as_ref().map(SyncUpdate::deadline)
+    }
+
+    fn apply_sync_update(&mut self) {
+        if let Some(update) = self.sync_update.take() {
+            self.parse(&update.buffer);
+        }
+    }
+
+    fn parse(&mut self, bytes: &[u8]) {
+        self.damaged = true;
+        // A pane scrolled back keeps showing the same rows while new output arrives.
+        let pushed = self.state.scrollback.pushed;
+        self.advance(bytes);
+        if self.scroll_offset > 0 {
+            let new_rows = self.state.scrollback.pushed.wrapping_sub(pushed);
+            self.scroll_offset = (self.scroll_offset + new_rows).min(self.history_rows());
+        }
+    }
+
+    fn advance(&mut self, mut bytes: &[u8]) {
+        while !bytes.is_empty() {
+            let parsed = self.parser.advance_until_terminated(&mut self.state, bytes);
+            bytes = &bytes[parsed..];
+            if !std::mem::take(&mut self.state.sync_requested) {
+                break;
+            }
+            // Hold the rest back until the synchronized update ends.
+            let mut update = SyncUpdate::new();
+            if !update.push(bytes) {
+                self.sync_update = Some(update);
+                return;
+            }
+        }
     }
 
     pub fn scroll_up(&mut self, amount: usize) {
-        let max_scroll = self
-            .state
-            .content_bottom_row
-            .saturating_sub(self.visible_rows.saturating_sub(1) as usize);
+        let max_scroll = self.history_rows();
         self.scroll_offset = (self.scroll_offset + amount).min(max_scroll);
+        self.damaged = true;
     }
 
     pub fn scroll_down(&mut self, amount: usize) {
         self.scroll_offset = self.scroll_offset.saturating_sub(amount);
+        self.damaged = true;
+    }
+
+    pub fn take_damage(&mut self) -> bool {
+        std::mem::take(&mut self.damaged)
+    }
+
+    pub fn render_dirty_rows(&mut self) {
+        let (viewport_top, viewport_bottom) = self.viewport();
+        for row_idx in viewport_top..=viewport_bottom {
+            if let Some(row) = self.line_mut(row_idx) {
+                row.render();
+            }
+        }
     }
 
     pub fn get_visible_lines(&self) -> Vec<Line<'_>> {
         let mut lines = Vec::with_capacity(self.visible_rows as usize);
 
-        let viewport_bottom = self
-            .state
-            .content_bottom_row
-            .saturating_sub(self.scroll_offset);
-        let viewport_top =
-            viewport_bottom.saturating_sub(self.visible_rows.saturating_sub(1) as usize);
-
+        let (viewport_top, viewport_bottom) = self.viewport();
         for row_idx in viewport_top..=viewport_bottom {
-            if let Some(row) = self.state.grid.row(row_idx) {
-                let mut spans: Vec<Span> = Vec::new();
-                for cell in row {
-                    let style = self.cell_to_ratatui_style(cell);
-                    if let Some(last) = spans.last_mut() {
-                        if last.style == style {
-                            last.content.to_mut().push(cell.c);
-                            continue;
-                        }
-                    }
-                    spans.push(Span::styled(cell.c.to_string(), style));
-                }
-                lines.push(Line::from(spans));
+            if let Some(row) = self.line(row_idx) {
+                lines.push(row.to_line());
             }
         }
         lines
     }
 
     pub fn get_cursor_position(&self) -> Option<(u16, u16)> {
-        let viewport_bottom = self
-            .state
-            .content_bottom_row
-            .saturating_sub(self.scroll_offset);
-        let viewport_top =
-            viewport_bottom.saturating_sub(self.visible_rows.saturating_sub(1) as usize);
+        let (viewport_top, viewport_bottom) = self.viewport();
+        let cursor_row = self.history_rows() + self.state.cursor_row;
 
-        if self.state.cursor_row >= viewport_top && self.state.cursor_row <= viewport_bottom {
-            let relative_y = self.state.cursor_row - viewport_top;
+        if cursor_row >= viewport_top && cursor_row <= viewport_bottom {
+            let relative_y = cursor_row - viewport_top;
             Some((self.state.cursor_col as u16, relative_y as u16))
         } else {
             None
         }
     }
 
-    fn cell_to_ratatui_style(&self, cell: &Cell) -> Style {
-        let mut style = Style::default();
-        style = style.fg(cell.fg);
-        style = style.bg(cell.bg);
-        if cell.flags.contains(CellFlags::BOLD) {
-            style = style.add_modifier(Modifier::BOLD);
+    /// The alternate screen has no scrollback.
+    fn history_rows(&self) -> usize {
+        if self.state.alt_screen_active {
+            0
+        } else {
+            self.state.scrollback.len()
         }
-        if cell.flags.contains(CellFlags::ITALIC) {
-            style = style.add_modifier(Modifier::ITALIC);
+    }
+
+    fn line(&self, index: usize) -> Option<&Row> {
+        let history_rows = self.history_rows();
+        if index < history_rows {
+            self.state.scrollback.line(index)
+        } else {
+            self.state.grid.line(index - history_rows)
         }
-        if cell.flags.contains(CellFlags::UNDERLINE) {
-            style = style.add_modifier(Modifier::UNDERLINED);
+    }
+
+    fn line_mut(&mut self, index: usize) -> Option<&mut Row> {
+        let history_rows = self.history_rows();
+        if index < history_rows {
+            self.state.scrollback.line_mut(index)
+        } else {
+            self.state.grid.line_mut(index - history_rows)
         }
-        if cell.flags.contains(CellFlags::INVERSE) {
-            style = style.add_modifier(Modifier::REVERSED);
+    }
+
+    fn viewport(&self) -> (usize, usize) {
+        let total_rows = self.history_rows() + self.state.grid.height();
+        let viewport_bottom = total_rows
+            .saturating_sub(1)
+            .saturating_sub(self.scroll_offset);
+        let viewport_top = (viewport_bottom + 1).saturating_sub(self.visible_rows as usize);
+        (viewport_top, viewport_bottom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen(vt: &VirtualTerminal) -> Vec<String> {
+        vt.get_visible_lines()
+            .iter()
+            .map(|line| {
+                let text: String = line
+                    .spans
+                    .iter()
+                    .map(|span| span.content.as_ref())
+                    .collect();
+                text.trim_end().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reflow_round_trip_restores_wrapped_lines() {
+        let mut vt = VirtualTerminal::new(4, 10, 100);
+        vt.process_bytes(b"0123456789abcde\r\nxyz");
+        let original = screen(&vt);
+        assert_eq!(original, ["0123456789", "abcde", "xyz", ""]);
+
+        vt.resize(4, 5);
+        assert_eq!(screen(&vt), ["01234", "56789", "abcde", "xyz"]);
+
+        vt.resize(4, 10);
+        assert_eq!(screen(&vt), original);
+    }
+
+    #[test]
+    fn reflow_keeps_cursor_on_the_same_character() {
+        let mut vt = VirtualTerminal::new(6, 10, 100);
+        vt.process_bytes(b"0123456789abc");
+        assert_eq!(vt.get_cursor_position(), Some((3, 1)));
+
+        vt.resize(6, 4);
+        assert_eq!(vt.get_cursor_position(), Some((1, 3)));
+        vt.process_bytes(b"X");
+        assert_eq!(screen(&vt)[3], "cX");
+
+        vt.resize(6, 10);
+        assert_eq!(vt.get_cursor_position(), Some((4, 1)));
+        assert_eq!(screen(&vt)[..2], ["0123456789", "abcX"]);
+    }
+
+    #[test]
+    fn reflow_moves_wide_char_across_wrap_boundary() {
+        let mut vt = VirtualTerminal::new(3, 5, 100);
+        vt.process_bytes("abcd中".as_bytes());
+        // The wide character does not fit in the last column and wraps whole.
+        assert_eq!(screen(&vt), ["abcd", "中", ""]);
+
+        vt.resize(3, 6);
+        assert_eq!(screen(&vt), ["abcd中", "", ""]);
+
+        vt.resize(3, 5);
+        assert_eq!(screen(&vt), ["abcd", "中", ""]);
+    }
+
+    #[test]
+    fn sync_update_ends_on_combined_mode_reset() {
+        let mut vt = VirtualTerminal::new(3, 10, 100);
+        vt.process_bytes(b"\x1b[?2026hAB");
+        assert_eq!(screen(&vt), ["", "", ""]);
+        vt.process_bytes(b"\x1b[?1049;20");
+        vt.process_bytes(b"26lC");
+        assert_eq!(screen(&vt), ["ABC", "", ""]);
+    }
+
+    #[test]
+    fn scrolled_back_view_stays_put_while_output_arrives() {
+        let mut vt = VirtualTerminal::new(2, 10, 100);
+        vt.process_bytes(b"1\r\n2\r\n3\r\n4");
+        vt.scroll_up(1);
+        assert_eq!(screen(&vt), ["2", "3"]);
+        vt.process_bytes(b"\r\n5\r\n6");
+        assert_eq!(screen(&vt), ["2", "3"]);
+
+        vt.scroll_down(10);
+        vt.process_bytes(b"\r\n7");
+        assert_eq!(screen(&vt), ["6", "7"]);
+    }
+
+    #[test]
+    fn resize_in_alt_screen_moves_saved_primary_cursor() {
+        let mut vt = VirtualTerminal::new(5, 10, 100);
+        vt.process_bytes(b"0123456789abcdefghij\r\n$ ");
+        vt.process_bytes(b"\x1b[?1049h");
+        vt.resize(5, 4);
+        vt.process_bytes(b"\x1b[?1049lX");
+        assert_eq!(screen(&vt), ["4567", "89ab", "cdef", "ghij", "$ X"]);
+    }
+
+    fn cell(vt: &VirtualTerminal, row: usize, col: usize) -> &Cell {
+        &vt.state.grid.row(row).unwrap()[col]
+    }
+
+    #[test]
+    fn sgr_indexed_and_rgb_colors() {
+        let mut vt = VirtualTerminal::new(2, 10, 100);
+        vt.process_bytes(b"\x1b[38;5;196ma\x1b[48;2;1;2;3mb\x1b[0m");
+        vt.process_bytes(b"\x1b[38:2:10:20:30mc\x1b[38:2::40:50:60md\x1b[38:2:1:70:80:90me");
+        vt.process_bytes(b"\x1b[48:5:42mf\x1b[39;49mg\x1b[101mh");
+        assert_eq!(cell(&vt, 0, 0).fg, Color::Indexed(196));
+        assert_eq!(cell(&vt, 0, 1).bg, Color::Rgb(1, 2, 3));
+        assert_eq!(cell(&vt, 0, 2).fg, Color::Rgb(10, 20, 30));
+        // Colon forms with an empty or explicit color space id.
+        assert_eq!(cell(&vt, 0, 3).fg, Color::Rgb(40, 50, 60));
+        assert_eq!(cell(&vt, 0, 4).fg, Color::Rgb(70, 80, 90));
+        assert_eq!(cell(&vt, 0, 5).bg, Color::Indexed(42));
+        assert_eq!(
+            (cell(&vt, 0, 6).fg, cell(&vt, 0, 6).bg),
+            (Color::Reset, Color::Reset)
+        );
+        assert_eq!(cell(&vt, 0, 7).bg, Color::LightRed);
+    }
+
+    #[test]
+    fn grid_scrolls_only_inside_region() {
+        let mut grid = Grid::new(5, 3);
+        for (row, c) in "abcde".chars().enumerate() {
+            grid.cell_mut(row, 0).unwrap().c = c;
         }
-        style
+        let text =
+            |grid: &Grid| -> String { (0..5).map(|row| grid.row(row).unwrap()[0].c).collect() };
+        grid.scroll_region_up(1, 3, 1, &Cell::default());
+        assert_eq!(text(&grid), "acd e");
+        grid.scroll_region_down(1, 3, 2, &Cell::default());
+        assert_eq!(text(&grid), "a  ce");
+    }
+
+    #[test]
+    fn index_and_reverse_index_respect_margins() {
+        let mut vt = VirtualTerminal::new(5, 5, 100);
+        vt.process_bytes(b"1\r\n2\r\n3\r\n4\r\n5\x1b[2;4r");
+        vt.process_bytes(b"\x1b[4;1H\x1bD");
+        assert_eq!(screen(&vt), ["1", "3", "4", "", "5"]);
+        vt.process_bytes(b"\x1b[2;1H\x1bM");
+        assert_eq!(screen(&vt), ["1", "", "3", "4", "5"]);
+        // Scrolling inside the margins never reaches the scrollback.
+        vt.scroll_up(10);
+        assert_eq!(screen(&vt)[0], "1");
+    }
+
+    #[test]
+    fn line_insert_delete_and_scroll_within_margins() {
+        let mut vt = VirtualTerminal::new(5, 5, 100);
+        vt.process_bytes(b"1\r\n2\r\n3\r\n4\r\n5\x1b[2;4r");
+        vt.process_bytes(b"\x1b[3;1H\x1b[L");
+        assert_eq!(screen(&vt), ["1", "2", "", "3", "5"]);
+        vt.process_bytes(b"\x1b[2M");
+        assert_eq!(screen(&vt), ["1", "2", "", "", "5"]);
+        vt.process_bytes(b"\x1b[2;1Hx\x1b[3;1Hy\x1b[4;1Hz\x1b[S");
+        assert_eq!(screen(&vt), ["1", "y", "z", "", "5"]);
+        vt.process_bytes(b"\x1b[2T");
+        assert_eq!(screen(&vt), ["1", "", "", "y", "5"]);
+    }
+
+    #[test]
+    fn character_insert_delete_erase_and_repeat() {
+        let mut vt = VirtualTerminal::new(2, 8, 100);
+        vt.process_bytes(b"abcdef\x1b[1;3H\x1b[2@");
+        assert_eq!(screen(&vt)[0], "ab  cdef");
+        vt.process_bytes(b"\x1b[3P");
+        assert_eq!(screen(&vt)[0], "abdef");
+        vt.process_bytes(b"\x1b[2X");
+        assert_eq!(screen(&vt)[0], "ab  f");
+        vt.process_bytes(b"\x1b[2;1Hx\x1b[3b");
+        assert_eq!(screen(&vt)[1], "xxxx");
+    }
+
+    #[test]
+    fn character_shifts_keep_attributes_and_erase_with_background() {
+        let mut vt = VirtualTerminal::new(1, 6, 100);
+        vt.process_bytes(b"a\x1b[1mb\x1b[0mc\x1b[1;1H\x1b[44m\x1b[@");
+        assert!(cell(&vt, 0, 2).flags.contains(CellFlags::BOLD));
+        assert_eq!(cell(&vt, 0, 0).bg, Color::Blue);
+        vt.process_bytes(b"\x1b[P");
+        assert!(cell(&vt, 0, 1).flags.contains(CellFlags::BOLD));
+        assert_eq!(cell(&vt, 0, 5).bg, Color::Blue);
+    }
+
+    fn responses(vt: &mut VirtualTerminal) -> String {
+        String::from_utf8(vt.take_responses().unwrap_or_default()).unwrap()
+    }
+
+    #[test]
+    fn decrqm_reports_mode_state() {
+        let mut vt = VirtualTerminal::new(3, 10, 100);
+        vt.process_bytes(b"\x1b[?2004$p\x1b[?2004h\x1b[?2004$p\x1b[?9999$p\x1b[4$p");
+        assert_eq!(
+            responses(&mut vt),
+            "\x1b[?2004;2$y\x1b[?2004;1$y\x1b[?9999;0$y\x1b[4;0$y"
+        );
+        vt.process_bytes(b"\x1b[?1049h\x1b[?1049$p");
+        assert_eq!(responses(&mut vt), "\x1b[?1049;1$y");
+    }
+
+    #[test]
+    fn cursor_position_and_status_reports() {
+        let mut vt = VirtualTerminal::new(5, 10, 100);
+        vt.process_bytes(b"\x1b[3;5H\x1b[6n\x1b[?6n\x1b[5n");
+        assert_eq!(responses(&mut vt), "\x1b[3;5R\x1b[?3;5R\x1b[0n");
+        // A cursor waiting to wrap reports the last column.
+        vt.process_bytes(b"\x1b[1;1H0123456789\x1b[6n");
+        assert_eq!(responses(&mut vt), "\x1b[1;10R");
     }
 }
@@ -1,14 +1,24 @@
 /* src/tui.rs */
 
 use crate::actions::Action;
-use crate::app::{App, BottomBarMode, HelpConflictDialogSelection, InputContext, ScriptEndStatus};
-use crate::config::{Config, Keybind};
+use crate::app::{
+    App, BottomBarMode, HelpConflictDialogSelection, InputContext, LogEntry, LogLevel,
+    ScriptEndStatus,
+};
+use crate::config::{ConcurrencyPolicy, Config, Keybind};
+use crate::event::{AppEvent, EventQueue};
+use crate::keys;
 use crate::project;
 use crate::shell::ShellProcess;
-use crate::ui::ui;
+use crate::task::{self, Task, TaskSpec};
+use crate::terminal::{ShellEvent, TermMode};
+use crate::ui::{shell_pane_inner_area, shell_pane_inner_size, ui};
 use anyhow::Result;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEventKind, KeyModifiers, MouseEvent, MouseEventKind,
+    },
     execute,
     terminal::{
         Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
@@ -18,11 +28,24 @@ use crossterm::{
 use ratatui::{
     Terminal,
     backend::{Backend, CrosstermBackend},
+    layout::Rect,
+};
+use std::{
+    collections::HashMap,
+    io::Write,
+    time::{Duration, Instant},
 };
-use std::{collections::HashMap, time::Duration};
 use strum::IntoEnumIterator;
 
-const CMD_FINISHED_MARKER: &str = "CLAY_CMD_FINISHED_MARKER_v1";
+const MOUSE_SCROLL_LINES: usize = 3;
+
+/// Draw at most about 60 frames a second, however fast output arrives.
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+const TASK_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Catches shell exits that leave the PTY open.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Initializes and runs the terminal user interface.
 pub fn run_tui() -> Result<()> {
@@ -34,110 +57,361 @@ pub fn run_tui() -> Result<()> {
 
     // Clear the terminal before entering alternate screen
     execute!(stdout, Clear(ClearType::All))?;
-    execute!(stdout, EnterAlternateScreen)?;
+    execute!(
+        stdout,
+        EnterAlternateScreen,
+        EnableMouseCapture,
+        EnableBracketedPaste
+    )?;
 
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
 
     let size = terminal.size()?;
-    let shell_pane_outer_height = size.height.saturating_sub(10);
-    let shell_pane_inner_height = shell_pane_outer_height.saturating_sub(2);
-    let shell_pane_inner_width = size.width.saturating_sub(2);
+    let (shell_pane_inner_width, shell_pane_inner_height) =
+        shell_pane_inner_size(Rect::new(0, 0, size.width, size.height));
 
+    let events = EventQueue::new();
     let mut app = App::new(
         shell_pane_inner_width,
         shell_pane_inner_height,
         config,
         project_config,
+        events.waker(),
     );
 
     // Updated project detection logic for logging
     if let Some(p_config) = &app.project_config {
         if p_config.scripts.get("dev") == Some(&"pnpm dev".to_string()) {
-            app.logs.push("pnpm detected. Config loaded.".to_string());
+            app.logs.push("pnpm detected. Config loaded.".into());
         } else if p_config.scripts.get("dev") == Some(&"cargo run".to_string()) {
-            app.logs.push("Rust detected. Config loaded.".to_string());
+            app.logs.push("Rust detected. Config loaded.".into());
         } else {
-            app.logs
-                .push("Project detected. Config loaded.".to_string());
+            app.logs.push("Project detected. Config loaded.".into());
         }
     } else {
-        app.logs.push("No project type detected.".to_string());
+        app.logs.push("No project type detected.".into());
+    }
+    for (action, key) in app.config.unbound_defaults.clone() {
+        app.logs.push(LogEntry::new(
+            LogLevel::Warning,
+            format!(
+                "{} was left unbound: its default key '{}' is already in use. Press 'h' to bind it.",
+                action.command_str(),
+                key
+            ),
+        ));
     }
 
-    let mut shell_process = ShellProcess::new(shell_pane_inner_height, shell_pane_inner_width)?;
+    let mut shell_process = ShellProcess::new(
+        &app.config.shell,
+        shell_pane_inner_height,
+        shell_pane_inner_width,
+        app.waker.clone(),
+    )?;
 
-    let result = run_app(&mut terminal, &mut app, &mut shell_process);
+    let result = run_app(&mut terminal, &mut app, &events, &mut shell_process);
 
     // Cleanup
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(
+        terminal.backend_mut(),
+        DisableBracketedPaste,
+        DisableMouseCapture,
+        LeaveAlternateScreen
+    )?;
     terminal.show_cursor()?;
 
     result
 }
 
-/// The main application loop.
+/// The main application loop. Sleeps until input or output arrives and only draws on changes.
 fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
+    events: &EventQueue,
     shell_process: &mut ShellProcess,
 ) -> Result<()> {
+    let mut needs_redraw = true;
+    let mut last_draw: Option<Instant> = None;
     loop {
-        terminal.draw(|f| ui(f, app))?;
-
-        if let Some(bytes) = shell_process.read_output_bytes() {
-            let mut output = String::from_utf8_lossy(&bytes).to_string();
-            let mut script_finished = false;
+        let since_draw = last_draw.map_or(FRAME_INTERVAL, |drawn| drawn.elapsed());
+        if needs_redraw && since_draw >= FRAME_INTERVAL {
+            app.viewed_terminal_mut().render_dirty_rows();
+            terminal.draw(|f| ui(f, app))?;
+            last_draw = Some(Instant::now());
+            needs_redraw = false;
+        }
 
-            if output.contains(CMD_FINISHED_MARKER) {
-                script_finished = true;
-                output = output.replace(CMD_FINISHED_MARKER, "");
-                output = output.trim_end().to_string();
+        let timeout = if needs_redraw {
+            FRAME_INTERVAL.saturating_sub(since_draw)
+        } else if app.tasks.iter().any(Task::is_running) {
+            TASK_POLL_INTERVAL
+        } else {
+            IDLE_POLL_INTERVAL
+        };
+        // Wake up in time to show a synchronized update that never ends.
+        let timeout = match app.viewed_terminal().sync_update_deadline() {
+            Some(deadline) => timeout.min(deadline.saturating_duration_since(Instant::now())),
+            None => timeout,
+        };
+        for event in events.wait(timeout)? {
+            match event {
+                AppEvent::Input(event) => {
+                    handle_event(event, terminal, app, shell_process)?;
+                    needs_redraw = true;
+                }
+                AppEvent::Output => {}
+                AppEvent::InputError(err) => return Err(err.into()),
+            }
+            if app.should_quit {
+                return Ok(());
             }
+        }
+
+        needs_redraw |= poll_processes(app, shell_process)?;
+        needs_redraw |= app.viewed_terminal_mut().take_damage();
+    }
+}
 
-            if !output.is_empty() {
-                app.terminal.process_bytes(output.as_bytes());
+fn handle_event<B: Backend>(
+    event: Event,
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    shell_process: &mut ShellProcess,
+) -> Result<()> {
+    match event {
+        Event::Key(key) => {
+            if key.kind != KeyEventKind::Press {
+                return Ok(());
+            }
+            if app.shell_focused {
+                handle_shell_focus_keys(key, app, shell_process)?;
+            } else if app.show_conflict_dialog {
+                handle_conflict_dialog_keys(key, app)?;
+            } else if app.is_editing_keybinding {
+                handle_help_edit_mode_keys(key, app);
+            } else if app.show_help {
+                handle_help_mode_keys(key, app)?;
+            } else {
+                handle_main_view_keys(key, app, shell_process)?;
             }
+        }
+        Event::Mouse(mouse) => {
+            let size = terminal.size()?;
+            let shell_area = shell_pane_inner_area(Rect::new(0, 0, size.width, size.height));
+            handle_mouse(mouse, app, shell_process, shell_area)?;
+        }
+        Event::Paste(text) => {
+            handle_paste(&text, app, shell_process)?;
+        }
+        Event::Resize(width, height) => {
+            handle_resize(app, shell_process, width, height)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
-            if script_finished {
-                app.finish_script(ScriptEndStatus::Finished);
+/// Returns whether anything besides the viewed terminal changed on screen.
+fn poll_processes(app: &mut App, shell_process: &mut ShellProcess) -> Result<bool> {
+    let mut changed = false;
+    for index in 0..app.tasks.len() {
+        match app.tasks[index].poll() {
+            Ok(Some(status)) => {
+                app.finish_task(index, status);
+                changed = true;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                let message = format!("Script '{}': {}", app.tasks[index].name, err);
+                app.logs.push(LogEntry::new(LogLevel::Error, message));
+                changed = true;
             }
         }
+    }
+    // The Status bar shows the signal a cancellation has reached, which advances on a timer.
+    changed |= app
+        .tasks
+        .iter()
+        .any(|task| task.is_running() && task.cancel_signal().is_some());
+    if let Some(spec) = app.next_queued_task() {
+        spawn_task(app, &spec)?;
+        changed = true;
+    }
 
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind != KeyEventKind::Press {
-                    continue;
-                }
-                if app.show_conflict_dialog {
-                    handle_conflict_dialog_keys(key, app)?;
-                } else if app.is_editing_keybinding {
-                    handle_help_edit_mode_keys(key, app);
-                } else if app.show_help {
-                    handle_help_mode_keys(key, app)?;
-                } else {
-                    handle_main_view_keys(key, app, shell_process)?;
-                }
+    app.terminal.flush_expired_sync_update();
+    if let Some(bytes) = shell_process.read_output_bytes() {
+        // The parser keeps partial UTF-8 and escape sequences between chunks.
+        app.terminal.process_bytes(&bytes);
+    }
+    if let Some(responses) = app.terminal.take_responses() {
+        shell_process.write_to_shell(&responses)?;
+    }
+    if let Some(clipboard_writes) = app.terminal.take_clipboard_writes() {
+        let mut stdout = std::io::stdout();
+        stdout.write_all(&clipboard_writes)?;
+        stdout.flush()?;
+    }
+    for event in app.terminal.take_shell_events() {
+        handle_shell_event(event, app);
+        changed = true;
+    }
+
+    if app.shell_status.is_none()
+        && let Some(exit_status) = shell_process.try_wait()?
+    {
+        app.shell_exited(shell_process.program(), task::end_status(&exit_status));
+        changed = true;
+    }
+    if app.should_respawn_shell {
+        let (rows, cols) = app.terminal.size();
+        match ShellProcess::new(&app.config.shell, rows, cols, app.waker.clone()) {
+            Ok(process) => {
+                *shell_process = process;
+                app.shell_respawned(shell_process.program());
             }
+            Err(err) => app.shell_respawn_failed(&err),
+        }
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// A finish mark only counts after the command started, so a late mark is ignored.
+fn handle_shell_event(event: ShellEvent, app: &mut App) {
+    match event {
+        ShellEvent::CommandExecuted if app.shell_command.is_some() => {
+            app.shell_command_executing = true;
+        }
+        ShellEvent::CommandFinished(exit_code) if app.shell_command_executing => {
+            app.finish_shell_command(ScriptEndStatus::from_exit_code(exit_code));
         }
+        _ => {}
+    }
+}
+
+fn handle_resize(app: &mut App, shell: &mut ShellProcess, width: u16, height: u16) -> Result<()> {
+    let (cols, rows) = shell_pane_inner_size(Rect::new(0, 0, width, height));
+    app.terminal.resize(rows, cols);
+    shell.resize(rows.max(1), cols.max(1))?;
+    for task in &mut app.tasks {
+        task.resize(rows, cols)?;
+    }
+    Ok(())
+}
 
-        if app.should_quit {
-            return Ok(());
+fn write_to_viewed(app: &mut App, shell: &mut ShellProcess, data: &[u8]) -> Result<()> {
+    match app.viewed_task {
+        Some(index) => app.tasks[index].write(data),
+        None => {
+            shell.write_to_shell(data)?;
+            Ok(())
         }
     }
 }
 
+fn handle_shell_focus_keys(
+    key: event::KeyEvent,
+    app: &mut App,
+    shell: &mut ShellProcess,
+) -> Result<()> {
+    if keys::same_key(key, app.config.shell_escape_chord()) {
+        app.shell_focused = false;
+        return Ok(());
+    }
+
+    let application_cursor = app.viewed_terminal().mode().contains(TermMode::APP_CURSOR);
+    if let Some(bytes) = keys::encode_key(key, application_cursor) {
+        write_to_viewed(app, shell, &bytes)?;
+    }
+    Ok(())
+}
+
+/// Mouse events go to the child if it enabled mouse tracking; otherwise the wheel scrolls.
+fn handle_mouse(
+    mouse: MouseEvent,
+    app: &mut App,
+    shell: &mut ShellProcess,
+    shell_area: Rect,
+) -> Result<()> {
+    if app.show_help || !shell_area.contains((mouse.column, mouse.row).into()) {
+        return Ok(());
+    }
+
+    let mode = app.viewed_terminal().mode();
+    if mode.intersects(TermMode::MOUSE_REPORTING) {
+        let col = mouse.column - shell_area.x;
+        let row = mouse.row - shell_area.y;
+        if let Some(bytes) = keys::encode_mouse(mouse.kind, mouse.modifiers, col, row, mode) {
+            write_to_viewed(app, shell, &bytes)?;
+        }
+        return Ok(());
+    }
+
+    match mouse.kind {
+        MouseEventKind::ScrollUp => app.viewed_terminal_mut().scroll_up(MOUSE_SCROLL_LINES),
+        MouseEventKind::ScrollDown => app.viewed_terminal_mut().scroll_down(MOUSE_SCROLL_LINES),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Pasted text never triggers actions.
+fn handle_paste(text: &str, app: &mut App, shell: &mut ShellProcess) -> Result<()> {
+    if app.shell_focused {
+        let bracketed = app
+            .viewed_terminal()
+            .mode()
+            .contains(TermMode::BRACKETED_PASTE);
+        let bytes = if bracketed {
+            // Drop any end marker inside the text so it cannot break out of the paste.
+            let text = text.replace("\x1b[201~", "");
+            format!("\x1b[200~{}\x1b[201~", text)
+        } else {
+            // Terminals send Enter as CR, and a pasted newline should behave the same.
+            text.replace("\r\n", "\r").replace('\n', "\r")
+        };
+        return write_to_viewed(app, shell, bytes.as_bytes());
+    }
+
+    if app.show_help {
+        return Ok(());
+    }
+
+    match app.bottom_bar_mode {
+        BottomBarMode::Tips | BottomBarMode::Status => {
+            app.bottom_bar_mode = BottomBarMode::Command;
+            app.reset_history_navigation();
+        }
+        BottomBarMode::Command => app.reset_history_navigation(),
+        BottomBarMode::Input => {}
+    }
+
+    // The palette is a single line, so line breaks become spaces.
+    let line = text
+        .trim_end_matches(['\r', '\n'])
+        .replace(['\r', '\n'], " ");
+    app.enter_str(&line);
+    Ok(())
+}
+
 fn handle_main_view_keys(
     key: event::KeyEvent,
     app: &mut App,
     shell: &mut ShellProcess,
 ) -> Result<()> {
     match app.bottom_bar_mode {
-        BottomBarMode::Tips => {
+        // While tasks run the Status bar replaces the tips, but actions stay available so
+        // other tasks can be started, viewed or cancelled.
+        BottomBarMode::Tips | BottomBarMode::Status => {
             if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
-                app.should_quit = true;
+                match app.running_task() {
+                    Some(index) => app.cancel_task(index),
+                    None if app.ctrl_c_quit_blocked() => {}
+                    None => app.should_quit = true,
+                }
                 return Ok(());
             }
 
@@ -151,28 +425,22 @@ fn handle_main_view_keys(
                 }
                 KeyCode::Up => app.scroll_up(),
                 KeyCode::Down => app.scroll_down(),
-                KeyCode::Esc => {
+                KeyCode::Esc if app.bottom_bar_mode == BottomBarMode::Tips => {
                     app.should_quit = true;
                 }
                 KeyCode::Char(c) => {
                     if let Some(action) = app.config.get_action_for_key(c) {
-                        dispatch_action(action, app, shell)?;
+                        dispatch_action(action, app)?;
                     }
                 }
                 _ => {}
             }
         }
-        BottomBarMode::Status => {
-            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
-                shell.write_to_shell(b"\x03")?;
-                app.finish_script(ScriptEndStatus::Cancelled);
-            }
-        }
         BottomBarMode::Command => {
             handle_command_mode_keys(key, app, shell)?;
         }
         BottomBarMode::Input => {
-            handle_input_mode_keys(key, app, shell)?;
+            handle_input_mode_keys(key, app)?;
         }
     }
     Ok(())
@@ -189,21 +457,29 @@ fn handle_command_mode_keys(
             app.submit_command();
 
             if input.starts_with('/') {
-                let parts = input.split_whitespace();
-                let command_str = parts.into_iter().next().unwrap_or("");
+                let mut parts = input.split_whitespace();
+                let command_str = parts.next().unwrap_or("");
 
                 let action_map: HashMap<&str, Action> =
                     Action::iter().map(|a| (a.command_str(), a)).collect();
 
-                if let Some(action) = action_map.get(command_str) {
-                    dispatch_action(*action, app, shell)?;
+                if action_map.get(command_str) == Some(&Action::DropQueued) {
+                    app.drop_queued_task(parts.next());
+                } else if let Some(action) = action_map.get(command_str) {
+                    dispatch_action(*action, app)?;
                 } else if command_str == "/exit" {
-                    dispatch_action(Action::Quit, app, shell)?;
+                    dispatch_action(Action::Quit, app)?;
                 }
+            } else if app.shell_status.is_some() {
+                app.logs.push(LogEntry::new(
+                    LogLevel::Warning,
+                    format!("Command '{}' not sent: the shell has exited.", input),
+                ));
             } else {
-                app.terminal.clear();
-                let command = format!("{}\n", input);
-                shell.write_to_shell(command.as_bytes())?;
+                let command_line = shell.command_line(&input);
+                shell.write_to_shell(command_line.as_bytes())?;
+                app.shell_command = Some(input);
+                app.shell_command_executing = false;
             }
         }
         KeyCode::Up => {
@@ -231,11 +507,7 @@ fn handle_command_mode_keys(
     Ok(())
 }
 
-fn handle_input_mode_keys(
-    key: event::KeyEvent,
-    app: &mut App,
-    shell: &mut ShellProcess,
-) -> Result<()> {
+fn handle_input_mode_keys(key: event::KeyEvent, app: &mut App) -> Result<()> {
     match key.code {
         KeyCode::Enter => {
             let user_input = app.command_input.trim().to_string();
@@ -250,15 +522,17 @@ fn handle_input_mode_keys(
             }
 
             if let Some(context) = context {
-                let (script_name, status) = match context {
-                    InputContext::AddPackage => ("add", "Adding dependencies"),
-                    InputContext::RemovePackage => ("remove", "Removing dependencies"),
-                    InputContext::CommitMessage => ("commit", "Committing"),
+                let (action, script_name, status) = match context {
+                    InputContext::AddPackage => (Action::AddPackage, "add", "Adding dependencies"),
+                    InputContext::RemovePackage => {
+                        (Action::RemovePackage, "remove", "Removing dependencies")
+                    }
+                    InputContext::CommitMessage => (Action::Commit, "commit", "Committing"),
                 };
 
                 if context == InputContext::CommitMessage {
                     let command = format!(r#"git add . && git commit -m "{}""#, user_input);
-                    run_shell_command(app, shell, "commit", &command, status)?;
+                    run_task(app, action, "commit", &command, status)?;
                 } else {
                     let command_to_run = app
                         .project_config
@@ -267,7 +541,7 @@ fn handle_input_mode_keys(
                         .map(|base_cmd| format!("{} {}", base_cmd, user_input));
 
                     if let Some(command) = command_to_run {
-                        run_shell_command(app, shell, script_name, &command, status)?;
+                        run_task(app, action, script_name, &command, status)?;
                     }
                 }
             }
@@ -318,8 +592,10 @@ fn handle_help_mode_keys(key: event::KeyEvent, app: &mut App) -> Result<()> {
 fn attempt_close_help(app: &mut App) -> Result<()> {
     if app.validate_and_prepare_to_close_help() {
         if let Err(e) = app.config.save() {
-            app.logs
-                .push(format!("Warning: Failed to save config: {}", e));
+            app.logs.push(LogEntry::new(
+                LogLevel::Warning,
+                format!("Warning: Failed to save config: {}", e),
+            ));
         }
     }
     Ok(())
@@ -350,8 +626,10 @@ fn handle_conflict_dialog_keys(key: event::KeyEvent, app: &mut App) -> Result<()
                 app.show_conflict_dialog = false;
                 app.show_help = false;
                 if let Err(e) = app.config.save() {
-                    app.logs
-                        .push(format!("Warning: Failed to save config: {}", e));
+                    app.logs.push(LogEntry::new(
+                        LogLevel::Warning,
+                        format!("Warning: Failed to save config: {}", e),
+                    ));
                 }
             }
             HelpConflictDialogSelection::Inspect => {
@@ -366,7 +644,7 @@ fn handle_conflict_dialog_keys(key: event::KeyEvent, app: &mut App) -> Result<()
     Ok(())
 }
 
-fn dispatch_action(action: Action, app: &mut App, shell: &mut ShellProcess) -> Result<()> {
+fn dispatch_action(action: Action, app: &mut App) -> Result<()> {
     match action {
         Action::Quit => app.should_quit = true,
         Action::ToggleHelp => app.show_help = true,
@@ -376,7 +654,25 @@ fn dispatch_action(action: Action, app: &mut App, shell: &mut ShellProcess) -> R
             app.bottom_bar_mode = BottomBarMode::Command;
             app.reset_history_navigation();
         }
-        Action::ClearShell => app.terminal.clear(),
+        Action::ClearShell => app.viewed_terminal_mut().clear(),
+        Action::FocusShell => {
+            app.shell_focused = true;
+            app.logs.push(
+                format!(
+                    "Shell focused. Press {} to return to Clay.",
+                    app.config.shell_escape_key
+                )
+                .into(),
+            );
+        }
+        Action::NextTask => app.view_next_task(),
+        Action::CancelTask => {
+            if let Some(index) = app.running_task() {
+                app.cancel_task(index);
+            }
+        }
+        Action::DropQueued => app.drop_queued_task(None),
+        Action::RespawnShell => app.respawn_shell(),
         Action::AddPackage => {
             app.bottom_bar_mode = BottomBarMode::Input;
             app.input_context = Some(InputContext::AddPackage);
@@ -389,50 +685,75 @@ fn dispatch_action(action: Action, app: &mut App, shell: &mut ShellProcess) -> R
             app.bottom_bar_mode = BottomBarMode::Input;
             app.input_context = Some(InputContext::CommitMessage);
         }
-        Action::Lint => run_shell_command(app, shell, "lint", "clay lint", "Formatting")?,
-        Action::Push => run_shell_command(app, shell, "push", "git push", "Pushing")?,
-        Action::LlmPush => {
-            run_shell_command(app, shell, "llm-push", "clay llm push", "AI Pushing")?
-        }
-        Action::ShowDiff => run_shell_command(app, shell, "diff", "clay diff", "Diffing")?,
+        Action::Lint => run_task(app, action, "lint", "clay lint", "Formatting")?,
+        Action::Push => run_task(app, action, "push", "git push", "Pushing")?,
+        Action::LlmPush => run_task(app, action, "llm-push", "clay llm push", "AI Pushing")?,
+        Action::ShowDiff => run_task(app, action, "diff", "clay diff", "Diffing")?,
         Action::GenerateMessage => {
-            run_shell_command(app, shell, "message", "clay llm commit", "Generating")?
+            run_task(app, action, "message", "clay llm commit", "Generating")?
         }
-        Action::VersionUpdate => run_shell_command(
+        Action::VersionUpdate => run_task(
             app,
-            shell,
+            action,
             "ver-update",
             "clay project update",
             "Versioning",
         )?,
 
-        Action::Run => execute_project_script(app, shell, "dev", "Running")?,
-        Action::Build => execute_project_script(app, shell, "build", "Building")?,
-        Action::Publish => execute_project_script(app, shell, "publish", "Publishing")?,
-        Action::Install => execute_project_script(app, shell, "install", "Installing")?,
-        Action::Clean => execute_project_script(app, shell, "clean", "Cleaning")?,
+        Action::Run => execute_project_script(app, action, "dev", "Running")?,
+        Action::Build => execute_project_script(app, action, "build", "Building")?,
+        Action::Publish => execute_project_script(app, action, "publish", "Publishing")?,
+        Action::Install => execute_project_script(app, action, "install", "Installing")?,
+        Action::Clean => execute_project_script(app, action, "clean", "Cleaning")?,
     }
     Ok(())
 }
 
-fn run_shell_command(
+/// Starts, queues or rejects a script according to the action's concurrency policy.
+fn run_task(
     app: &mut App,
-    shell: &mut ShellProcess,
+    action: Action,
     script_name: &str,
     command: &str,
     status: &str,
 ) -> Result<()> {
-    app.terminal.clear();
-    let full_command_with_marker = format!("{}\necho {}\n", command, CMD_FINISHED_MARKER);
-    shell.write_to_shell(full_command_with_marker.as_bytes())?;
-    let message = format!("{} (Press Ctrl+c to cancel)...", status);
-    app.start_script(script_name, &message);
+    let policy = app.config.concurrency_policy(action);
+    let spec = TaskSpec {
+        name: script_name.to_string(),
+        command: command.to_string(),
+        status_message: status.to_string(),
+        exclusive: policy != ConcurrencyPolicy::Parallel,
+    };
+    match (policy, app.blocking_task()) {
+        (ConcurrencyPolicy::Queue, Some(_)) => app.queue_task(spec),
+        (ConcurrencyPolicy::Reject, Some(index)) => app.logs.push(LogEntry::new(
+            LogLevel::Warning,
+            format!(
+                "Script '{}' not started: '{}' is still running.",
+                script_name, app.tasks[index].name
+            ),
+        )),
+        _ => spawn_task(app, &spec)?,
+    }
+    Ok(())
+}
+
+fn spawn_task(app: &mut App, spec: &TaskSpec) -> Result<()> {
+    let (rows, cols) = app.terminal.size();
+    let task = Task::spawn(
+        spec,
+        rows,
+        cols,
+        app.config.scrollback_lines,
+        app.waker.clone(),
+    )?;
+    app.start_task(task);
     Ok(())
 }
 
 fn execute_project_script(
     app: &mut App,
-    shell: &mut ShellProcess,
+    action: Action,
     script_name: &str,
     status: &str,
 ) -> Result<()> {
@@ -443,7 +764,7 @@ fn execute_project_script(
     };
 
     if let Some(command) = command_to_run {
-        run_shell_command(app, shell, script_name, &command, status)?;
+        run_task(app, action, script_name, &command, status)?;
     }
     Ok(())
 }
@@ -1,7 +1,10 @@
 /* src/ui.rs */
 
 use crate::actions::Action;
-use crate::app::{App, BottomBarMode, HelpConflictDialogSelection, InputContext};
+use crate::app::{
+    App, BottomBarMode, HelpConflictDialogSelection, InputContext, LogLevel, ScriptEndStatus,
+    signal_label,
+};
 use crate::config::Keybind;
 use ratatui::{
     Frame,
@@ -10,19 +13,44 @@ use ratatui::{
     text::{Line, Span, Text},
     widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, Wrap},
 };
+use std::rc::Rc;
+use unicode_width::UnicodeWidthStr;
 
-pub fn ui(frame: &mut Frame, app: &App) {
-    let chunks = Layout::default()
+const TASK_PANE_WIDTH: u16 = 32;
+
+fn main_layout(area: Rect) -> Rc<[Rect]> {
+    Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Min(0),
             Constraint::Length(7),
             Constraint::Length(3),
         ])
-        .split(frame.area());
+        .split(area)
+}
+
+pub fn shell_pane_inner_area(area: Rect) -> Rect {
+    Block::default()
+        .borders(Borders::ALL)
+        .inner(main_layout(area)[0])
+}
+
+pub fn shell_pane_inner_size(area: Rect) -> (u16, u16) {
+    let inner = shell_pane_inner_area(area);
+    (inner.width, inner.height)
+}
+
+pub fn ui(frame: &mut Frame, app: &App) {
+    let chunks = main_layout(frame.area());
+
+    let log_row = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Min(0), Constraint::Length(TASK_PANE_WIDTH)])
+        .split(chunks[1]);
 
     render_shell_pane(frame, app, chunks[0]);
-    render_logs_pane(frame, app, chunks[1]);
+    render_logs_pane(frame, app, log_row[0]);
+    render_task_pane(frame, app, log_row[1]);
     render_bottom_bar(frame, app, chunks[2]);
     update_cursor(frame, app, chunks[0], chunks[2]);
 
@@ -36,14 +64,46 @@ pub fn ui(frame: &mut Frame, app: &App) {
 }
 
 fn render_shell_pane(frame: &mut Frame, app: &App, area: Rect) {
-    let block = Block::default().borders(Borders::ALL).title("Shell");
-    let lines = app.terminal.get_visible_lines();
+    let terminal = app.viewed_terminal();
+    let mut title = match app.viewed_task {
+        Some(index) => {
+            let task = &app.tasks[index];
+            let state = task
+                .status
+                .map_or_else(|| "running".to_string(), |status| status.summary());
+            format!("Task: {} ({})", task.name, state)
+        }
+        None => match app.shell_status {
+            Some(status) => format!("Shell ({})", status.summary()),
+            None => "Shell".to_string(),
+        },
+    };
+    if let Some(terminal_title) = terminal.title() {
+        title = format!("{}: {}", title, terminal_title);
+    }
+    let mut block = Block::default().borders(Borders::ALL).title(title);
+    if app.shell_focused {
+        block = block.border_style(Style::default().fg(Color::Cyan));
+    }
+    let lines = terminal.get_visible_lines();
     let paragraph = Paragraph::new(lines).block(block);
     frame.render_widget(paragraph, area);
 }
 
 fn render_logs_pane(frame: &mut Frame, app: &App, area: Rect) {
-    let text: Vec<Line> = app.logs.iter().map(|l| Line::from(l.clone())).collect();
+    let text: Vec<Line> = app
+        .logs
+        .iter()
+        .map(|entry| {
+            let style = match entry.level {
+                LogLevel::Info => Style::default(),
+                LogLevel::Success => Style::default().fg(Color::Green),
+                LogLevel::Warning => Style::default().fg(Color::Yellow),
+                LogLevel::Error => Style::default().fg(Color::Red),
+            };
+            Line::styled(entry.message.clone(), style)
+        })
+        .collect();
     let paragraph = Paragraph::new(text)
         .block(Block::default().borders(Borders::ALL).title("Logs"))
         .wrap(Wrap { trim: true });
@@ -58,7 +118,69 @@ fn render_logs_pane(frame: &mut Frame, app: &App, area: Rect) {
     }
 }
 
+fn render_task_pane(frame: &mut Frame, app: &App, area: Rect) {
+    let entry = |name: &str, state: Span<'static>, viewed: bool| {
+        let marker = if viewed { "> " } else { "  " };
+        let name_style = if viewed {
+            Style::default().add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        Line::from(vec![
+            Span::raw(marker),
+            Span::styled(format!("{} ", name), name_style),
+            state,
+        ])
+    };
+
+    let shell_state = match app.shell_status {
+        Some(status) => Span::styled(
+            status.summary(),
+            Style::default().fg(script_status_color(status)),
+        ),
+        None => Span::raw(""),
+    };
+    let mut lines = vec![entry("Shell", shell_state, app.viewed_task.is_none())];
+    for (index, task) in app.tasks.iter().enumerate() {
+        let state = match task.status {
+            Some(status) => Span::styled(
+                status.summary(),
+                Style::default().fg(script_status_color(status)),
+            ),
+            None => Span::styled("running", Style::default().fg(Color::Cyan)),
+        };
+        lines.push(entry(&task.name, state, app.viewed_task == Some(index)));
+    }
+
+    // Keep the viewed entry visible when there are more tasks than rows.
+    let viewed_line = app.viewed_task.map_or(0, |index| index + 1);
+    let panel_height = area.height.saturating_sub(2) as usize;
+    let scroll = (viewed_line + 1).saturating_sub(panel_height) as u16;
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Tasks"))
+        .scroll((scroll, 0));
+    frame.render_widget(paragraph, area);
+}
+
+fn script_status_color(status: ScriptEndStatus) -> Color {
+    match status {
+        ScriptEndStatus::Finished => Color::Green,
+        ScriptEndStatus::Failed(_) | ScriptEndStatus::Signalled(_) => Color::Red,
+        ScriptEndStatus::Cancelled(_) => Color::Yellow,
+    }
+}
+
 fn render_bottom_bar(frame: &mut Frame, app: &App, area: Rect) {
+    if app.shell_focused {
+        let content = format!(
+            "Keys are sent to the shell. Press {} to return to Clay.",
+            app.config.shell_escape_key
+        );
+        let block = Block::default().borders(Borders::ALL).title("Shell Focus");
+        frame.render_widget(Paragraph::new(content).block(block), area);
+        return;
+    }
+
     let (title, content) = match app.bottom_bar_mode {
         BottomBarMode::Tips => {
             let mut tips = vec!["[/]Cmd".to_string()];
@@ -73,6 +195,9 @@ fn render_bottom_bar(frame: &mut Frame, app: &App, area: Rect) {
                 (Action::Commit, "Commit"),
                 (Action::Install, "Install"),
                 (Action::Clean, "Clean"),
+                (Action::FocusShell, "Shell"),
+                (Action::NextTask, "Tasks"),
+                (Action::CancelTask, "Stop"),
             ];
 
             for (action, name) in tip_map {
@@ -107,10 +232,50 @@ fn render_bottom_bar(frame: &mut Frame, app: &App, area: Rect) {
             };
             ("Input", format!("{}{}", prompt, app.command_input))
         }
-        BottomBarMode::Status => ("Status", app.status_message.clone()),
+        BottomBarMode::Status => {
+            let content = match app.running_task() {
+                Some(index) => {
+                    let task = &app.tasks[index];
+                    let mut message = match task.cancel_signal() {
+                        Some(signal) => format!(
+                            "{}: cancelling, sent {} (Press Ctrl+c to escalate)...",
+                            task.status_message,
+                            signal_label(signal)
+                        ),
+                        None => format!("{} (Press Ctrl+c to cancel)...", task.status_message),
+                    };
+                    let running = app.tasks.iter().filter(|task| task.is_running()).count();
+                    if running > 1 {
+                        message.push_str(&format!(" [{} more running]", running - 1));
+                    }
+                    if !app.queued_tasks.is_empty() {
+                        let queued: Vec<String> = app
+                            .queued_tasks
+                            .iter()
+                            .enumerate()
+                            .map(|(index, spec)| format!("{}.{}", index + 1, spec.name))
+                            .collect();
+                        message.push_str(&format!(" | Queued: {}", queued.join(", ")));
+                    }
+                    message
+                }
+                None => String::new(),
+            };
+            ("Status", content)
+        }
     };
 
-    let block = Block::default().borders(Borders::ALL).title(title);
+    let mut block = Block::default().borders(Borders::ALL).title(title);
+    if let Some((script, status)) = &app.last_script_status {
+        let summary = Line::from(vec![
+            Span::raw(format!(" Last: {} ", script)),
+            Span::styled(
+                format!("{} ", status.summary()),
+                Style::default().fg(script_status_color(*status)),
+            ),
+        ]);
+        block = block.title(summary.right_aligned());
+    }
     let paragraph = Paragraph::new(content).block(block);
     frame.render_widget(paragraph, area);
 }
@@ -120,6 +285,13 @@ fn update_cursor(frame: &mut Frame, app: &App, shell_area: Rect, bottom_bar_area
         return; // No cursor in help mode
     }
 
+    if app.shell_focused {
+        if let Some((x, y)) = app.viewed_terminal().get_cursor_position() {
+            frame.set_cursor_position((shell_area.x + 1 + x, shell_area.y + 1 + y));
+        }
+        return;
+    }
+
     match app.bottom_bar_mode {
         BottomBarMode::Command | BottomBarMode::Input => {
             let prompt_offset = match app.bottom_bar_mode {
@@ -134,13 +306,14 @@ fn update_cursor(frame: &mut Frame, app: &App, shell_area: Rect, bottom_bar_area
                 _ => 0,
             };
 
-            let cursor_x =
-                bottom_bar_area.x + 1 + prompt_offset as u16 + app.command_cursor_position as u16;
+            // Wide characters before the cursor take two columns each.
+            let cursor_offset = app.command_input[..app.command_cursor_byte_index()].width();
+            let cursor_x = bottom_bar_area.x + 1 + prompt_offset as u16 + cursor_offset as u16;
             let cursor_y = bottom_bar_area.y + 1;
             frame.set_cursor_position((cursor_x, cursor_y));
         }
         BottomBarMode::Tips => {
-            if let Some((x, y)) = app.terminal.get_cursor_position() {
+            if let Some((x, y)) = app.viewed_terminal().get_cursor_position() {
                 let cursor_x = shell_area.x + 1 + x;
                 let cursor_y = shell_area.y + 1 + y;
                 frame.set_cursor_position((cursor_x, cursor_y));